@@ -0,0 +1,16 @@
+use std::io;
+use std::process;
+
+use json_mini::lsp::run;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    match run(stdin.lock(), stdout.lock()) {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("json-mini-lsp: {}", err);
+            process::exit(1);
+        },
+    }
+}
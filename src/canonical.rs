@@ -0,0 +1,190 @@
+use crate::object::DuplicateKeyPolicy;
+use crate::parser::{parse_json_with, ParseOptions};
+use crate::value::{escape_json_string, JsonValue};
+
+/// Serializes `value` in the JSON Canonicalization Scheme of RFC 8785: object
+/// members sorted by the UTF-16 code units of their keys, numbers written the way
+/// ECMAScript's `Number.prototype.toString` writes doubles, minimal string escapes
+/// and no whitespace.
+pub fn canonicalize_value(value: &JsonValue) -> Result<String, String> {
+    let mut out = String::new();
+    write_canonical(value, &mut out)?;
+    Ok(out)
+}
+
+fn write_canonical(value: &JsonValue, out: &mut String) -> Result<(), String> {
+    match value {
+        JsonValue::Null => out.push_str("null"),
+        JsonValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        JsonValue::Number(n) => out.push_str(&format_es_number(n.as_f64()?)?),
+        JsonValue::String(s) => out.push_str(&escape_json_string(s)),
+        JsonValue::Array(arr) => {
+            out.push('[');
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 { out.push(','); }
+                write_canonical(item, out)?;
+            }
+            out.push(']');
+        },
+        JsonValue::Object(obj) => {
+            let mut members: Vec<(&String, &JsonValue)> = obj.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, value)) in members.into_iter().enumerate() {
+                if i > 0 { out.push(','); }
+                out.push_str(&escape_json_string(key));
+                out.push(':');
+                write_canonical(value, out)?;
+            }
+            out.push('}');
+        },
+    }
+    Ok(())
+}
+
+/// Formats a double like ECMAScript's Number-to-String conversion (ECMA-262, 7.1.12.1).
+pub fn format_es_number(value: f64) -> Result<String, String> {
+    if !value.is_finite() {
+        return Err(format!("{} cannot be represented in canonical JSON", value));
+    }
+    if value == 0.0 {
+        return Ok(String::from("0")); // -0 as well
+    }
+    if value < 0.0 {
+        return Ok(format!("-{}", format_es_number(-value)?));
+    }
+    let (digits, n) = shortest_digits(value);
+    let k = digits.len() as i32;
+
+    let text = if k <= n && n <= 21 {
+        digits + &"0".repeat((n - k) as usize)
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat((-n) as usize), digits)
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        if k == 1 {
+            format!("{}e{}{}", digits, sign, (n - 1).abs())
+        } else {
+            format!("{}.{}e{}{}", &digits[..1], &digits[1..], sign, (n - 1).abs())
+        }
+    };
+    Ok(text)
+}
+
+/// Splits `d.ddde<exp>` into its digits and the exponent `n` of ECMA-262, where
+/// the value is `0.digits * 10^n`.
+fn split_scientific(scientific: &str) -> (String, i32) {
+    let (mantissa, exp) = scientific.split_once('e').unwrap_or((scientific, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    (digits, exp.parse::<i32>().unwrap_or(0) + 1)
+}
+
+/// Shortest digits that read back as `value`. {:e} already finds those, but when
+/// two candidates are equally close it rounds the tie up, where ECMAScript picks
+/// the even one, so look at the exact expansion to settle ties.
+fn shortest_digits(value: f64) -> (String, i32) {
+    let (digits, n) = split_scientific(&format!("{:e}", value));
+    let k = digits.len();
+    // 1100 places is enough to print any finite double exactly
+    let (exact, exact_n) = split_scientific(&format!("{:.1100e}", value));
+    if exact_n != n || !exact[k..].starts_with('5') || exact[k + 1..].bytes().any(|b| b != b'0') {
+        return (digits, n);
+    }
+    let down = String::from(&exact[..k]);
+    let reads_back = |candidate: &str| {
+        format!("0.{}e{}", candidate, n).parse::<f64>().ok() == Some(value)
+    };
+    let down_is_even = down.bytes().last().is_some_and(|b| (b - b'0').is_multiple_of(2));
+    if down != digits && down_is_even && reads_back(&down) {
+        return (String::from(down.trim_end_matches('0')), n);
+    }
+    (digits, n)
+}
+
+/// Parses `in_json` and writes it back out in RFC 8785 canonical form. Input with
+/// repeated keys is rejected, since the scheme only accepts I-JSON.
+pub fn canonicalize_json(in_json: &str) -> Result<String, String> {
+    let options = ParseOptions { duplicate_keys: DuplicateKeyPolicy::Error, ..Default::default() };
+    let value = parse_json_with(in_json, &options)?;
+    canonicalize_value(&value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonicalize_json, format_es_number};
+
+    #[test]
+    fn test_format_es_number_rfc8785_vectors() {
+        // Appendix B of RFC 8785
+        let vectors: [(u64, &str); 24] = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+        for (bits, expected) in vectors {
+            assert_eq!(format_es_number(f64::from_bits(bits)).unwrap(), expected, "{:#018x}", bits);
+        }
+        assert!(format_es_number(f64::NAN).is_err());
+        assert!(format_es_number(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_canonicalize_rfc8785_example() {
+        // section 3.2.2 of RFC 8785
+        let input = r#"{
+  "numbers": [333333333.33333329, 1E30, 4.50,
+              2e-3, 0.000000000000000000000000001],
+  "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+  "literals": [null, true, false]
+}"#;
+        let expected = "{\"literals\":[null,true,false],\"numbers\":[333333333.3333333,1e+30,4.5,0.002,1e-27],\
+\"string\":\"\u{20ac}$\\u000f\\nA'B\\\"\\\\\\\\\\\"/\"}";
+        assert_eq!(canonicalize_json(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_canonicalize_sorts_by_utf16() {
+        // section 3.2.3 of RFC 8785
+        let input = r#"{
+  "\u20ac": "Euro Sign",
+  "\r": "Carriage Return",
+  "\ufb33": "Hebrew Letter Dalet With Dagesh",
+  "1": "One",
+  "\ud83d\ude00": "Emoji: Grinning Face",
+  "\u0080": "Control",
+  "\u00f6": "Latin Small Letter O With Diaeresis"
+}"#;
+        let expected = "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+\"\u{f6}\":\"Latin Small Letter O With Diaeresis\",\"\u{20ac}\":\"Euro Sign\",\
+\"\u{1f600}\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}";
+        assert_eq!(canonicalize_json(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_canonicalize_rejects_duplicates() {
+        assert!(canonicalize_json(r#"{"a":1,"a":2}"#).is_err());
+    }
+}
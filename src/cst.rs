@@ -0,0 +1,658 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use crate::parser::{parse_json, too_deep, MAX_DEPTH};
+use crate::pointer::{parse_array_index, parse_pointer};
+use crate::position::Span;
+use crate::recover::{lex_tolerant, Lexeme};
+use crate::value::{escape_json_string, unescape_json_string, JsonValue};
+use crate::{JSONLexer, Token, TriviaToken, NO_IGNORE_WS};
+
+/// A JSON document as written, whitespace and comments and all. Edits change
+/// only the tokens they are about, so everything else prints back exactly as
+/// it was.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cst {
+    root: CstNode,
+    /// Carries the whitespace and comments after the root value.
+    eof: TriviaToken,
+}
+
+/// A value in the tree, with the tokens it was written with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CstNode {
+    /// A string, number, `true`, `false` or `null`.
+    Scalar(TriviaToken),
+    /// An object or an array, depending on the opening token.
+    Container { open: TriviaToken, items: Vec<CstItem>, close: TriviaToken },
+}
+
+/// An object member or an array element, with the comma after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CstItem {
+    /// The key and the colon of an object member; `None` in an array.
+    pub key: Option<(TriviaToken, TriviaToken)>,
+    pub value: CstNode,
+    pub comma: Option<TriviaToken>,
+}
+
+// How new text is laid out, taken from what the document already does.
+struct Style {
+    /// One level of indentation.
+    unit: String,
+    /// Whitespace between a key and its colon, and after the colon.
+    before_colon: String,
+    after_colon: String,
+}
+
+impl Cst {
+    /// Builds the tree of a valid JSON document, which may have `//` and
+    /// `/* */` comments in it. Comments are kept as trivia, like whitespace.
+    pub fn parse(input: &str) -> Result<Cst, String> {
+        // the tree is only built from valid documents, so the parser does the
+        // checking, on a copy with the comments blanked out
+        let plain = blank_comments(input)?;
+        parse_json(&plain)?;
+        let mut lexer = JSONLexer::from(plain, NO_IGNORE_WS);
+        lexer.lex()?;
+        let mut tokens = lexer.attach_trivia();
+        // the blanks are where the comments were, byte for byte
+        for token in &mut tokens {
+            token.leading = String::from(&input[token.span.start - token.leading.len()..token.span.start]);
+            token.trailing = String::from(&input[token.span.end..token.span.end + token.trailing.len()]);
+        }
+        let mut tokens = tokens.into_iter().peekable();
+        let root = parse_node(&mut tokens, 0)?;
+        match tokens.next() {
+            Some(eof) if eof.token == Token::Eof => Ok(Cst { root, eof }),
+            Some(other) => Err(unexpected(&other)),
+            None => Err(String::from("Unexpected end of input")),
+        }
+    }
+
+    pub fn root(&self) -> &CstNode {
+        &self.root
+    }
+
+    pub fn to_value(&self) -> Result<JsonValue, String> {
+        Ok(parse_json(&blank_comments(&self.to_string())?)?)
+    }
+
+    /// Replaces the value at `pointer`, or adds it if its parent exists and it
+    /// doesn't: a new member of an object, or an element appended to an array
+    /// (`-` or the array's length as the last token).
+    pub fn set(&mut self, pointer: &str, value: &JsonValue) -> Result<(), String> {
+        let tokens = parse_pointer(pointer)?;
+        let style = Style::detect(&self.root);
+        let Some((last, parent_tokens)) = tokens.split_last() else {
+            let indent = String::from(indentation(&self.root.first().leading));
+            let multiline = self.root.is_multiline();
+            return self.root.replace(value, multiline, &indent, &style);
+        };
+        let (parent, indent) = self.root.walk_mut(parent_tokens, String::new())
+            .ok_or_else(|| format!("Parent of '{}' does not exist", pointer))?;
+        match parent.item_index(last) {
+            Some(i) => {
+                let multiline = parent.is_multiline();
+                let indent = parent.item_indent(i, &indent);
+                let CstNode::Container { items, .. } = parent else { unreachable!() };
+                items[i].value.replace(value, multiline, &indent, &style)
+            },
+            None => parent.insert_at(last, value, &indent, &style, pointer),
+        }
+    }
+
+    /// Adds a member to an object, or inserts an element into an array before
+    /// the given index (`-` appends). Setting an existing member is an error.
+    pub fn insert(&mut self, pointer: &str, value: &JsonValue) -> Result<(), String> {
+        let tokens = parse_pointer(pointer)?;
+        let style = Style::detect(&self.root);
+        let (last, parent_tokens) = tokens.split_last().ok_or("Cannot insert the whole document")?;
+        let (parent, indent) = self.root.walk_mut(parent_tokens, String::new())
+            .ok_or_else(|| format!("Parent of '{}' does not exist", pointer))?;
+        if parent.is_object() && parent.item_index(last).is_some() {
+            return Err(format!("'{}' already exists", pointer));
+        }
+        parent.insert_at(last, value, &indent, &style, pointer)
+    }
+
+    /// Removes a member or an element along with its comma.
+    pub fn remove(&mut self, pointer: &str) -> Result<(), String> {
+        let tokens = parse_pointer(pointer)?;
+        let (last, parent_tokens) = tokens.split_last().ok_or("Cannot remove the whole document")?;
+        let (parent, _) = self.root.walk_mut(parent_tokens, String::new())
+            .ok_or_else(|| format!("'{}' does not exist", pointer))?;
+        let i = parent.item_index(last).ok_or_else(|| format!("'{}' does not exist", pointer))?;
+        parent.remove_item(i);
+        Ok(())
+    }
+
+    /// Renames the object member at `pointer`, leaving its value and position alone.
+    pub fn rename_key(&mut self, pointer: &str, new_key: &str) -> Result<(), String> {
+        let tokens = parse_pointer(pointer)?;
+        let (last, parent_tokens) = tokens.split_last().ok_or("Cannot rename the whole document")?;
+        let (parent, _) = self.root.walk_mut(parent_tokens, String::new())
+            .ok_or_else(|| format!("'{}' does not exist", pointer))?;
+        if !parent.is_object() {
+            return Err(format!("'{}' is not an object member", pointer));
+        }
+        let i = parent.item_index(last).ok_or_else(|| format!("'{}' does not exist", pointer))?;
+        if new_key != last && parent.item_index(new_key).is_some() {
+            return Err(format!("The object already has a member named '{}'", new_key));
+        }
+        let CstNode::Container { items, .. } = parent else { unreachable!() };
+        if let Some((key, _)) = &mut items[i].key {
+            key.token = Token::StringLiteral(escape_json_string(new_key));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        self.root.write_source(&mut out);
+        write_token(&self.eof, &mut out);
+        f.write_str(&out)
+    }
+}
+
+impl CstNode {
+    pub fn is_object(&self) -> bool {
+        matches!(self, CstNode::Container { open, .. } if open.token == Token::OpenBrace('{'))
+    }
+
+    /// Where the node's tokens are in the text it was parsed from. Nodes added by
+    /// an edit have spans into their own rendering instead.
+    pub fn span(&self) -> Span {
+        self.first().span.to(self.last().span)
+    }
+
+    fn first(&self) -> &TriviaToken {
+        match self {
+            CstNode::Scalar(token) => token,
+            CstNode::Container { open, .. } => open,
+        }
+    }
+
+    fn first_mut(&mut self) -> &mut TriviaToken {
+        match self {
+            CstNode::Scalar(token) => token,
+            CstNode::Container { open, .. } => open,
+        }
+    }
+
+    fn last(&self) -> &TriviaToken {
+        match self {
+            CstNode::Scalar(token) => token,
+            CstNode::Container { close, .. } => close,
+        }
+    }
+
+    fn last_mut(&mut self) -> &mut TriviaToken {
+        match self {
+            CstNode::Scalar(token) => token,
+            CstNode::Container { close, .. } => close,
+        }
+    }
+
+    fn write_source(&self, out: &mut String) {
+        match self {
+            CstNode::Scalar(token) => write_token(token, out),
+            CstNode::Container { open, items, close } => {
+                write_token(open, out);
+                for item in items {
+                    if let Some((key, colon)) = &item.key {
+                        write_token(key, out);
+                        write_token(colon, out);
+                    }
+                    item.value.write_source(out);
+                    if let Some(comma) = &item.comma {
+                        write_token(comma, out);
+                    }
+                }
+                write_token(close, out);
+            },
+        }
+    }
+
+    // whether the node's own text (not its outer trivia) spans several lines
+    fn is_multiline(&self) -> bool {
+        let mut out = String::new();
+        self.write_source(&mut out);
+        let first = self.first();
+        let last = self.last();
+        out[first.leading.len()..out.len() - last.trailing.len()].contains('\n')
+    }
+
+    // the index of the item a pointer token names, the last of repeated keys
+    fn item_index(&self, token: &str) -> Option<usize> {
+        let CstNode::Container { items, .. } = self else { return None };
+        if self.is_object() {
+            items.iter().rposition(|item| item.key_text().as_deref() == Some(token))
+        } else {
+            parse_array_index(token).filter(|&i| i < items.len())
+        }
+    }
+
+    // whether item `i` is the first thing on its line
+    fn item_starts_line(&self, i: usize) -> bool {
+        let CstNode::Container { open, items, .. } = self else { return false };
+        let before = if i == 0 { open } else { items[i - 1].last() };
+        before.trailing.ends_with('\n') || items[i].first().leading.contains('\n')
+    }
+
+    // the indentation of the line item `i` is on
+    fn item_indent(&self, i: usize, container_indent: &str) -> String {
+        let CstNode::Container { items, .. } = self else { return String::from(container_indent) };
+        if self.item_starts_line(i) {
+            String::from(indentation(&items[i].first().leading))
+        } else {
+            String::from(container_indent)
+        }
+    }
+
+    // follows `tokens` down from this node, tracking the indentation of the
+    // line each node starts on
+    fn walk_mut(&mut self, tokens: &[String], indent: String) -> Option<(&mut CstNode, String)> {
+        let Some((first, rest)) = tokens.split_first() else { return Some((self, indent)) };
+        let i = self.item_index(first)?;
+        let indent = self.item_indent(i, &indent);
+        let CstNode::Container { items, .. } = self else { return None };
+        items[i].value.walk_mut(rest, indent)
+    }
+
+    // swaps in `value`, keeping the whitespace around the old one
+    fn replace(&mut self, value: &JsonValue, multiline: bool, indent: &str, style: &Style) -> Result<(), String> {
+        let mut node = parse_rendered(&render(value, multiline, indent, style))?;
+        node.first_mut().leading = std::mem::take(&mut self.first_mut().leading);
+        node.last_mut().trailing = std::mem::take(&mut self.last_mut().trailing);
+        *self = node;
+        Ok(())
+    }
+
+    // adds a member named `token` to an object, or an element before index
+    // `token` of an array
+    fn insert_at(&mut self, token: &str, value: &JsonValue, indent: &str, style: &Style, pointer: &str) -> Result<(), String> {
+        let CstNode::Container { items, .. } = self else {
+            return Err(format!("Cannot add '{}' to a scalar value", pointer));
+        };
+        let len = items.len();
+        if self.is_object() {
+            let key = TriviaToken { leading: String::new(), token: Token::StringLiteral(escape_json_string(token)), span: Span::default(), trailing: style.before_colon.clone() };
+            let colon = TriviaToken { leading: String::new(), token: Token::Colon(':'), span: Span::default(), trailing: style.after_colon.clone() };
+            return self.insert_item(len, Some((key, colon)), value, indent, style);
+        }
+        let index = match token {
+            "-" => len,
+            _ => parse_array_index(token).filter(|&i| i <= len)
+                .ok_or_else(|| format!("'{}' is not a valid position in an array of length {}", pointer, len))?,
+        };
+        self.insert_item(index, None, value, indent, style)
+    }
+
+    fn insert_item(&mut self, index: usize, key: Option<(TriviaToken, TriviaToken)>, value: &JsonValue, indent: &str, style: &Style) -> Result<(), String> {
+        let multiline = self.is_multiline();
+        let len = match self {
+            CstNode::Container { items, .. } => items.len(),
+            CstNode::Scalar(_) => return Err(String::from("Cannot add to a scalar value")),
+        };
+        let item_indent = if multiline && len > 0 {
+            self.item_indent(index.min(len - 1), indent)
+        } else if multiline {
+            format!("{}{}", indent, style.unit)
+        } else {
+            String::from(indent)
+        };
+        let CstNode::Container { open, items, close } = self else { unreachable!() };
+        // reuse the whitespace between the first two items, or after the opening bracket
+        let (after_comma, before_item) = match items.len() {
+            0 => (String::from("\n"), item_indent.clone()),
+            1 => (plain_trailing(&open.trailing), plain_leading(&items[0].first().leading)),
+            _ => (items[0].comma.as_ref().map_or(String::new(), |comma| plain_trailing(&comma.trailing)), plain_leading(&items[1].first().leading)),
+        };
+        let mut item = CstItem { key, value: parse_rendered(&render(value, multiline, &item_indent, style))?, comma: None };
+        let comma = |trailing: String| TriviaToken { leading: String::new(), token: Token::Comma(','), span: Span::default(), trailing };
+
+        if items.is_empty() {
+            if multiline {
+                if !has_comment(&open.trailing) {
+                    open.trailing = after_comma;
+                }
+                // a comment inside the empty container ends up above the item
+                let mut leading = String::new();
+                if has_comment(&close.leading) {
+                    leading.push_str(close.leading.trim_end_matches([' ', '\t']));
+                    if !leading.ends_with('\n') {
+                        leading.push('\n');
+                    }
+                }
+                item.first_mut().leading = leading + &before_item;
+                item.last_mut().trailing = String::from("\n");
+                close.leading = String::from(indent);
+            }
+        } else if index == items.len() {
+            let last = items.last_mut().unwrap();
+            let trailing = std::mem::take(&mut last.value.last_mut().trailing);
+            if has_comment(&trailing) {
+                // the comment stays after the value it is about, behind the new comma
+                item.last_mut().trailing = String::from(if trailing.ends_with('\n') { "\n" } else { "" });
+                last.comma = Some(comma(trailing));
+            } else {
+                item.last_mut().trailing = trailing;
+                last.comma = Some(comma(after_comma));
+            }
+            item.first_mut().leading = before_item;
+        } else {
+            let next = items[index].first_mut();
+            item.first_mut().leading = if has_comment(&next.leading) {
+                before_item
+            } else {
+                std::mem::replace(&mut next.leading, before_item)
+            };
+            item.comma = Some(comma(after_comma));
+        }
+        items.insert(index, item);
+        Ok(())
+    }
+
+    fn remove_item(&mut self, index: usize) {
+        let CstNode::Container { open, items, close } = self else { return };
+        let removed = items.remove(index);
+        if items.is_empty() {
+            if !has_comment(&open.trailing) {
+                open.trailing.clear();
+            }
+            if !has_comment(&close.leading) {
+                close.leading.clear();
+            }
+        } else if index == items.len() {
+            // the new last item loses its comma, but not a comment after it
+            let last = items.last_mut().unwrap();
+            let comma = last.comma.take().map(|comma| comma.trailing).filter(|trailing| has_comment(trailing));
+            last.value.last_mut().trailing = comma.unwrap_or_else(|| plain_trailing(&removed.value.last().trailing));
+        }
+    }
+}
+
+impl CstItem {
+    /// The member's key, decoded; `None` for array elements.
+    pub fn key_text(&self) -> Option<String> {
+        let (key, _) = self.key.as_ref()?;
+        let Token::StringLiteral(raw) = &key.token else { return None };
+        unescape_json_string(raw).ok()
+    }
+
+    fn first(&self) -> &TriviaToken {
+        match &self.key {
+            Some((key, _)) => key,
+            None => self.value.first(),
+        }
+    }
+
+    fn first_mut(&mut self) -> &mut TriviaToken {
+        match &mut self.key {
+            Some((key, _)) => key,
+            None => self.value.first_mut(),
+        }
+    }
+
+    fn last(&self) -> &TriviaToken {
+        self.comma.as_ref().unwrap_or_else(|| self.value.last())
+    }
+
+    fn last_mut(&mut self) -> &mut TriviaToken {
+        match &mut self.comma {
+            Some(comma) => comma,
+            None => self.value.last_mut(),
+        }
+    }
+}
+
+impl Style {
+    fn detect(root: &CstNode) -> Style {
+        let mut style = Style { unit: String::from(" "), before_colon: String::new(), after_colon: String::new() };
+        if let Some((key, colon)) = first_member(root) {
+            style.before_colon = key.trailing.clone();
+            style.after_colon = colon.trailing.clone();
+        }
+        if let Some(unit) = indent_unit(root, indentation(&root.first().leading)) {
+            style.unit = unit;
+        }
+        style
+    }
+}
+
+fn first_member(node: &CstNode) -> Option<(&TriviaToken, &TriviaToken)> {
+    let CstNode::Container { items, .. } = node else { return None };
+    items.iter().find_map(|item| match &item.key {
+        Some((key, colon)) if !colon.trailing.contains('\n') && !has_comment(&key.trailing) && !has_comment(&colon.trailing) => Some((key, colon)),
+        _ => first_member(&item.value),
+    })
+}
+
+// the first step in indentation from a container to an item on its own line
+fn indent_unit(node: &CstNode, indent: &str) -> Option<String> {
+    let CstNode::Container { items, .. } = node else { return None };
+    (0..items.len()).find_map(|i| {
+        let item_indent = node.item_indent(i, indent);
+        match item_indent.strip_prefix(indent) {
+            Some(unit) if !unit.is_empty() => Some(String::from(unit)),
+            _ => indent_unit(&items[i].value, &item_indent),
+        }
+    })
+}
+
+// the spaces and tabs after the last line break of some leading trivia
+fn indentation(leading: &str) -> &str {
+    let line = leading.rsplit('\n').next().unwrap_or("");
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+// Trivia is whitespace and comments only, so any slash in it starts a comment.
+fn has_comment(trivia: &str) -> bool {
+    trivia.contains('/')
+}
+
+// The layout of some trivia without its comments, for copying to new items:
+// the indentation of leading trivia, or whether trailing trivia ends the line.
+fn plain_leading(leading: &str) -> String {
+    String::from(if has_comment(leading) { indentation(leading) } else { leading })
+}
+
+fn plain_trailing(trailing: &str) -> String {
+    match trailing {
+        _ if !has_comment(trailing) => String::from(trailing),
+        _ if trailing.contains('\n') => String::from("\n"),
+        _ => String::from(" "),
+    }
+}
+
+// `input` with every byte of its comments turned into a space, so it lexes as
+// JSON with the same offsets; newlines in block comments go too, so that
+// trivia is never split inside one.
+fn blank_comments(input: &str) -> Result<String, String> {
+    let mut bytes = input.as_bytes().to_vec();
+    for (lexeme, span) in lex_tolerant(input) {
+        if lexeme != Lexeme::Comment {
+            continue;
+        }
+        let text = &input[span.start..span.end];
+        if text.starts_with("/*") && (text.len() < 4 || !text.ends_with("*/")) {
+            return Err(format!("Unterminated comment starting at position {}", span.start));
+        }
+        bytes[span.start..span.end].fill(b' ');
+    }
+    Ok(String::from_utf8(bytes).expect("comments are replaced whole"))
+}
+
+fn write_token(token: &TriviaToken, out: &mut String) {
+    out.push_str(&token.leading);
+    out.push_str(&token.token.clone().extract_value());
+    out.push_str(&token.trailing);
+}
+
+// `value` as text in the document's style; containers break over lines
+// indented from `indent` when `multiline` is set
+fn render(value: &JsonValue, multiline: bool, indent: &str, style: &Style) -> String {
+    let inner = format!("{}{}", indent, style.unit);
+    let separator = if multiline { format!(",\n{}", inner) } else if style.after_colon.is_empty() { String::from(",") } else { String::from(", ") };
+    let (open, close) = if multiline { (format!("\n{}", inner), format!("\n{}", indent)) } else { (String::new(), String::new()) };
+    match value {
+        JsonValue::Array(arr) if !arr.is_empty() => {
+            let items: Vec<String> = arr.iter().map(|item| render(item, multiline, &inner, style)).collect();
+            format!("[{}{}{}]", open, items.join(&separator), close)
+        },
+        JsonValue::Object(obj) if !obj.is_empty() => {
+            let members: Vec<String> = obj.iter()
+                .map(|(key, item)| format!("{}{}:{}{}", escape_json_string(key), style.before_colon, style.after_colon, render(item, multiline, &inner, style)))
+                .collect();
+            format!("{{{}{}{}}}", open, members.join(&separator), close)
+        },
+        _ => value.to_json_string(),
+    }
+}
+
+fn parse_rendered(text: &str) -> Result<CstNode, String> {
+    let mut lexer = JSONLexer::from(String::from(text), NO_IGNORE_WS);
+    lexer.lex()?;
+    let mut tokens = lexer.attach_trivia().into_iter().peekable();
+    parse_node(&mut tokens, 0)
+}
+
+fn unexpected(token: &TriviaToken) -> String {
+    format!("Unexpected '{}' at position {}", token.token.clone().extract_value(), token.span.start)
+}
+
+fn parse_node(tokens: &mut Peekable<IntoIter<TriviaToken>>, depth: usize) -> Result<CstNode, String> {
+    let open = tokens.next().ok_or("Unexpected end of input")?;
+    if depth == MAX_DEPTH && matches!(open.token, Token::OpenBrace(_) | Token::OpenBrack(_)) {
+        return Err(format!("{} at position {}", too_deep(MAX_DEPTH), open.span.start));
+    }
+    let closer = match open.token {
+        Token::OpenBrace(_) => Token::CloseBrace('}'),
+        Token::OpenBrack(_) => Token::CloseBrack(']'),
+        Token::StringLiteral(_) | Token::NumericLiteral(_) | Token::BoolLiteral(_) | Token::NullLiteral(_) => {
+            return Ok(CstNode::Scalar(open));
+        },
+        _ => return Err(unexpected(&open)),
+    };
+    let is_object = closer == Token::CloseBrace('}');
+    let mut items = vec![];
+    while tokens.peek().is_some_and(|token| token.token != closer) {
+        let key = if is_object {
+            let key = tokens.next().ok_or("Unexpected end of input")?;
+            let colon = tokens.next().ok_or("Unexpected end of input")?;
+            if !matches!(key.token, Token::StringLiteral(_)) || colon.token != Token::Colon(':') {
+                return Err(unexpected(&colon));
+            }
+            Some((key, colon))
+        } else {
+            None
+        };
+        let value = parse_node(tokens, depth + 1)?;
+        let comma = tokens.next_if(|token| token.token == Token::Comma(','));
+        let done = comma.is_none();
+        items.push(CstItem { key, value, comma });
+        if done {
+            break;
+        }
+    }
+    let close = tokens.next().ok_or("Unexpected end of input")?;
+    if close.token != closer {
+        return Err(unexpected(&close));
+    }
+    Ok(CstNode::Container { open, items, close })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::Cst;
+    use crate::parser::parse_json;
+
+    fn edited(input: &str, edit: impl FnOnce(&mut Cst) -> Result<(), String>) -> String {
+        let mut cst = Cst::parse(input).unwrap();
+        edit(&mut cst).unwrap();
+        let output = cst.to_string();
+        Cst::parse(&output).unwrap();
+        output
+    }
+
+    #[test]
+    fn test_cst_round_trip() {
+        for entry in fs::read_dir("testfiles").unwrap() {
+            let input = fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert_eq!(Cst::parse(&input).unwrap().to_string(), input);
+        }
+        assert!(Cst::parse("{\"a\": }").is_err());
+        let commented = "// settings\n{\n  /* the first */ \"a\": 1, // one\n  \"b\": [2 /* two */]\n}\n/* end */";
+        assert_eq!(Cst::parse(commented).unwrap().to_string(), commented);
+        assert_eq!(Cst::parse(commented).unwrap().to_value(), Ok(parse_json("{\"a\":1,\"b\":[2]}").unwrap()));
+        assert!(Cst::parse("{\"a\": 1 /* one\n}").is_err());
+        assert!(Cst::parse(&format!("{}{}", "[".repeat(100_000), "]".repeat(100_000))).is_err());
+    }
+
+    #[test]
+    fn test_cst_set_touches_only_the_value() {
+        let input = fs::read_to_string("testfiles/long_test.json").unwrap();
+        let output = edited(&input, |cst| cst.set("/web-app/servlet/0/init-param/useJSP", &parse_json("true").unwrap()));
+        assert_eq!(output, input.replacen("\"useJSP\": false", "\"useJSP\": true", 1));
+
+        let replaced = edited("{\n  \"a\": 1,\n  \"b\": 2\n}\n", |cst| cst.set("/a", &parse_json("{\"x\": [1, 2]}").unwrap()));
+        assert_eq!(replaced, "{\n  \"a\": {\n    \"x\": [\n      1,\n      2\n    ]\n  },\n  \"b\": 2\n}\n");
+        assert_eq!(edited("[1, 2]", |cst| cst.set("", &parse_json("{\"a\":1}").unwrap())), "{\"a\":1}");
+    }
+
+    #[test]
+    fn test_cst_insert() {
+        let pretty = "{\n    \"a\": 1\n}\n";
+        assert_eq!(edited(pretty, |cst| cst.set("/b", &parse_json("[true]").unwrap())), "{\n    \"a\": 1,\n    \"b\": [\n        true\n    ]\n}\n");
+        assert_eq!(edited("{\"a\": 1, \"b\": 2}", |cst| cst.insert("/c", &parse_json("{\"d\":3}").unwrap())), "{\"a\": 1, \"b\": 2, \"c\": {\"d\": 3}}");
+        assert_eq!(edited("[ 1, 2 ]", |cst| cst.insert("/0", &parse_json("0").unwrap())), "[ 0, 1, 2 ]");
+        assert_eq!(edited("[1,2]", |cst| cst.insert("/-", &parse_json("3").unwrap())), "[1,2,3]");
+        assert_eq!(edited("{\"a\": {}}", |cst| cst.set("/a/b", &parse_json("1").unwrap())), "{\"a\": {\"b\": 1}}");
+        assert_eq!(edited("{\n  \"a\": []\n}", |cst| cst.insert("/a/0", &parse_json("1").unwrap())), "{\n  \"a\": [1]\n}");
+
+        let mut cst = Cst::parse("{\"a\": 1}").unwrap();
+        assert!(cst.insert("/a", &parse_json("2").unwrap()).is_err());
+        assert!(cst.set("/x/y", &parse_json("2").unwrap()).is_err());
+        assert!(cst.set("/a/b", &parse_json("2").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_cst_remove_and_rename() {
+        let input = "{\n  \"a\": 1,\n  \"b\": [1, 2, 3],\n  \"c\": 3\n}";
+        assert_eq!(edited(input, |cst| cst.remove("/a")), "{\n  \"b\": [1, 2, 3],\n  \"c\": 3\n}");
+        assert_eq!(edited(input, |cst| cst.remove("/c")), "{\n  \"a\": 1,\n  \"b\": [1, 2, 3]\n}");
+        assert_eq!(edited(input, |cst| cst.remove("/b/1")), "{\n  \"a\": 1,\n  \"b\": [1, 3],\n  \"c\": 3\n}");
+        assert_eq!(edited("{\n  \"a\": 1\n}", |cst| cst.remove("/a")), "{}");
+        assert_eq!(edited(input, |cst| cst.rename_key("/b", "list")), input.replace("\"b\"", "\"list\""));
+
+        let mut cst = Cst::parse(input).unwrap();
+        assert!(cst.remove("/z").is_err());
+        assert!(cst.rename_key("/a", "c").is_err());
+        assert!(cst.rename_key("/b/0", "x").is_err());
+    }
+
+    #[test]
+    fn test_cst_edits_keep_comments() {
+        let input = "{\n  // port to listen on\n  \"port\": 80, // default\n  \"host\": \"a\" // local\n}\n";
+        assert_eq!(edited(input, |cst| cst.set("/port", &parse_json("8080").unwrap())), input.replace("80,", "8080,"));
+        assert_eq!(
+            edited(input, |cst| cst.insert("/debug", &parse_json("true").unwrap())),
+            "{\n  // port to listen on\n  \"port\": 80, // default\n  \"host\": \"a\", // local\n  \"debug\": true\n}\n"
+        );
+        assert_eq!(
+            edited("[\n  // one\n  1,\n  2\n]", |cst| cst.insert("/0", &parse_json("0").unwrap())),
+            "[\n  0,\n  // one\n  1,\n  2\n]"
+        );
+        assert_eq!(edited(input, |cst| cst.remove("/host")), "{\n  // port to listen on\n  \"port\": 80 // default\n}\n");
+        assert_eq!(
+            edited("{\n  \"a\": {\n    // nothing yet\n  }\n}", |cst| cst.insert("/a/b", &parse_json("1").unwrap())),
+            "{\n  \"a\": {\n    // nothing yet\n    \"b\": 1\n  }\n}"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::error::JsonError;
+use crate::position::LineIndex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl Level {
+    fn name(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Level::Error => RED,
+            Level::Warning => YELLOW,
+        }
+    }
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// longest stretch of a source line shown; minified files are one huge line
+const MAX_SNIPPET_CHARS: usize = 100;
+
+/// Renders `error` against its source the way compilers do:
+///
+/// ```text
+/// error: 'True' is not a JSON value
+///  --> config.json:2:8
+///   |
+/// 2 |   "a": True,
+///   |        ^^^^
+///   = help: JSON booleans are lowercase
+/// ```
+pub fn render_diagnostic(source: &str, file_name: &str, level: Level, error: &JsonError, color: bool) -> String {
+    let paint = |code: &str, text: &str| if color { format!("{}{}{}", code, text, RESET) } else { String::from(text) };
+    let index = LineIndex::new(source);
+    let start = index.line_col(error.span.start);
+    let line_span = index.line_span(start.line).unwrap_or_default();
+    let line_text = &source[line_span.start..line_span.end];
+
+    // the part of the line under the span, in chars from the line start
+    let underline_from = start.col - 1;
+    let span_end = error.span.end.clamp(error.span.start, line_span.end.max(error.span.start));
+    let underline_len = source.get(error.span.start..span_end).map_or(0, |text| text.chars().count()).max(1);
+
+    // keep long lines to a window around the span
+    let line_chars = line_text.chars().count();
+    let window_start = if line_chars > MAX_SNIPPET_CHARS { underline_from.saturating_sub(MAX_SNIPPET_CHARS / 3) } else { 0 };
+    let window_end = (window_start + MAX_SNIPPET_CHARS).min(line_chars);
+    let mut shown: String = line_text.chars().skip(window_start).take(window_end - window_start).collect();
+    let mut caret_pad: String = line_text.chars().skip(window_start).take(underline_from.saturating_sub(window_start))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    if window_start > 0 {
+        shown.insert_str(0, "...");
+        caret_pad.insert_str(0, "   ");
+    }
+    if window_end < line_chars {
+        shown.push_str("...");
+    }
+    let underline_len = underline_len.min((window_end + 1).saturating_sub(underline_from)).max(1);
+
+    let gutter = start.line.to_string().len();
+    let blank = " ".repeat(gutter);
+    let mut out = format!("{}{}\n", paint(level.color(), level.name()), paint(BOLD, &format!(": {}", error.message)));
+    out.push_str(&format!("{}{} {}:{}\n", blank, paint(BLUE, "-->"), file_name, start));
+    out.push_str(&format!("{} {}\n", blank, paint(BLUE, "|")));
+    out.push_str(&format!("{} {} {}\n", paint(BLUE, &start.line.to_string()), paint(BLUE, "|"), shown));
+    out.push_str(&format!("{} {} {}{}\n", blank, paint(BLUE, "|"), caret_pad, paint(level.color(), &"^".repeat(underline_len))));
+    if let Some(help) = &error.help {
+        out.push_str(&format!("{} {} {}\n", blank, paint(BLUE, "="), paint(CYAN, &format!("help: {}", help))));
+    }
+    out
+}
+
+/// Every diagnostic in turn, separated by blank lines.
+pub fn render_diagnostics(source: &str, file_name: &str, level: Level, errors: &[JsonError], color: bool) -> String {
+    errors.iter()
+        .map(|error| render_diagnostic(source, file_name, level, error, color))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_diagnostic, render_diagnostics, Level};
+    use crate::error::JsonError;
+    use crate::position::Span;
+
+    #[test]
+    fn test_render_diagnostic() {
+        let source = "{\n  \"a\": True,\n  \"b\": 1\n}";
+        let error = JsonError::new("'True' is not a JSON value", Span::new(9, 13)).with_help("JSON booleans are lowercase");
+        assert_eq!(render_diagnostic(source, "config.json", Level::Error, &error, false), "\
+error: 'True' is not a JSON value
+ --> config.json:2:8
+  |
+2 |   \"a\": True,
+  |        ^^^^
+  = help: JSON booleans are lowercase
+");
+        let colored = render_diagnostic(source, "config.json", Level::Error, &error, true);
+        assert!(colored.starts_with("\x1b[1;31merror\x1b[0m"));
+    }
+
+    #[test]
+    fn test_render_empty_span_and_end_of_input() {
+        let source = "[1\n";
+        let errors = [JsonError::new("Missing ','", Span::new(2, 2)), JsonError::new("Unclosed '['", Span::new(0, 1))];
+        let rendered = render_diagnostics(source, "a.json", Level::Warning, &errors, false);
+        assert_eq!(rendered, "\
+warning: Missing ','
+ --> a.json:1:3
+  |
+1 | [1
+  |   ^
+
+warning: Unclosed '['
+ --> a.json:1:1
+  |
+1 | [1
+  | ^
+");
+    }
+
+    #[test]
+    fn test_render_long_line_window() {
+        let source = format!("[{}, tru]", "1, ".repeat(200));
+        let start = source.find("tru").unwrap();
+        let error = JsonError::new("'tru' is not a JSON value", Span::new(start, start + 3));
+        let rendered = render_diagnostic(&source, "big.json", Level::Error, &error, false);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert!(lines[3].starts_with("1 | ...") && lines[3].ends_with("tru]"));
+        assert!(lines[3].len() < 120);
+        let caret = lines[4].find('^').unwrap();
+        assert_eq!(&lines[3][caret..caret + 3], "tru");
+    }
+}
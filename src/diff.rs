@@ -0,0 +1,199 @@
+use crate::object::JsonObject;
+use crate::parser::parse_json;
+use crate::pointer::push_pointer_token;
+use crate::value::JsonValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+    /// Same members, different key order. Only reported unless key order is ignored.
+    Reordered,
+}
+
+impl DiffKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DiffKind::Added => "added",
+            DiffKind::Removed => "removed",
+            DiffKind::Changed => "changed",
+            DiffKind::Reordered => "reordered",
+        }
+    }
+}
+
+/// One difference between two documents, addressed by JSON Pointer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    pub kind: DiffKind,
+    pub path: String,
+    /// The value in the first document, if it has one at `path`. For `Reordered`
+    /// this is the array of keys in their old order.
+    pub old: Option<JsonValue>,
+    /// The value in the second document, if it has one at `path`.
+    pub new: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Treat objects with the same members in a different order as equal.
+    pub ignore_key_order: bool,
+}
+
+/// Structural differences between `a` and `b`. Numbers compare by value, so
+/// `1.0` and `1` are equal; arrays are compared index by index.
+pub fn diff_values(a: &JsonValue, b: &JsonValue, options: &DiffOptions) -> Vec<DiffEntry> {
+    let mut entries = vec![];
+    diff_at(a, b, String::new(), options, &mut entries);
+    entries
+}
+
+pub fn diff_json(a: &str, b: &str, options: &DiffOptions) -> Result<Vec<DiffEntry>, String> {
+    let a = parse_json(a).map_err(|err| format!("first document: {}", err))?;
+    let b = parse_json(b).map_err(|err| format!("second document: {}", err))?;
+    Ok(diff_values(&a, &b, options))
+}
+
+fn diff_at(a: &JsonValue, b: &JsonValue, path: String, options: &DiffOptions, entries: &mut Vec<DiffEntry>) {
+    match (a, b) {
+        (JsonValue::Object(a_obj), JsonValue::Object(b_obj)) => {
+            if !options.ignore_key_order {
+                if let Some((old_order, new_order)) = key_order_change(a_obj, b_obj) {
+                    entries.push(DiffEntry { kind: DiffKind::Reordered, path: path.clone(), old: Some(old_order), new: Some(new_order) });
+                }
+            }
+            for (key, a_value) in a_obj.iter() {
+                let child = push_pointer_token(&path, key);
+                match b_obj.get(key) {
+                    Some(b_value) => diff_at(a_value, b_value, child, options, entries),
+                    None => entries.push(DiffEntry { kind: DiffKind::Removed, path: child, old: Some(a_value.clone()), new: None }),
+                }
+            }
+            for (key, b_value) in b_obj.iter() {
+                if !a_obj.contains_key(key) {
+                    let child = push_pointer_token(&path, key);
+                    entries.push(DiffEntry { kind: DiffKind::Added, path: child, old: None, new: Some(b_value.clone()) });
+                }
+            }
+        },
+        (JsonValue::Array(a_arr), JsonValue::Array(b_arr)) => {
+            for (i, a_value) in a_arr.iter().enumerate() {
+                let child = push_pointer_token(&path, &i.to_string());
+                match b_arr.get(i) {
+                    Some(b_value) => diff_at(a_value, b_value, child, options, entries),
+                    None => entries.push(DiffEntry { kind: DiffKind::Removed, path: child, old: Some(a_value.clone()), new: None }),
+                }
+            }
+            for (i, b_value) in b_arr.iter().enumerate().skip(a_arr.len()) {
+                let child = push_pointer_token(&path, &i.to_string());
+                entries.push(DiffEntry { kind: DiffKind::Added, path: child, old: None, new: Some(b_value.clone()) });
+            }
+        },
+        _ => {
+            if a != b {
+                entries.push(DiffEntry { kind: DiffKind::Changed, path, old: Some(a.clone()), new: Some(b.clone()) });
+            }
+        },
+    }
+}
+
+// the keys both objects share, in each object's order, if those orders differ
+fn key_order_change(a: &JsonObject, b: &JsonObject) -> Option<(JsonValue, JsonValue)> {
+    let a_keys: Vec<&String> = a.keys().filter(|k| b.contains_key(k)).collect();
+    let b_keys: Vec<&String> = b.keys().filter(|k| a.contains_key(k)).collect();
+    if a_keys == b_keys {
+        return None;
+    }
+    let to_array = |keys: Vec<&String>| JsonValue::Array(keys.into_iter().map(|k| JsonValue::from(k.as_str())).collect());
+    Some((to_array(a_keys), to_array(b_keys)))
+}
+
+/// Machine-readable form: an array of `{"kind", "path", "old", "new"}` objects,
+/// leaving out `old`/`new` where there is no value.
+pub fn diff_to_json(entries: &[DiffEntry]) -> JsonValue {
+    let items = entries.iter().map(|entry| {
+        let mut obj = JsonObject::new();
+        obj.insert(String::from("kind"), JsonValue::from(entry.kind.name()));
+        obj.insert(String::from("path"), JsonValue::from(entry.path.as_str()));
+        if let Some(old) = &entry.old {
+            obj.insert(String::from("old"), old.clone());
+        }
+        if let Some(new) = &entry.new {
+            obj.insert(String::from("new"), new.clone());
+        }
+        JsonValue::Object(obj)
+    }).collect();
+    JsonValue::Array(items)
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// One line per entry: `+ path: value`, `- path: value`, `~ path: old -> new`,
+/// with ANSI colors if `color` is set.
+pub fn render_diff(entries: &[DiffEntry], color: bool) -> String {
+    let paint = |code: &str, text: String| if color { format!("{}{}{}", code, text, RESET) } else { text };
+    let show = |value: &Option<JsonValue>| value.as_ref().map(|v| v.to_json_string()).unwrap_or_default();
+    let mut out = String::new();
+    for entry in entries {
+        let path = if entry.path.is_empty() { "(root)" } else { &entry.path };
+        let line = match entry.kind {
+            DiffKind::Added => paint(GREEN, format!("+ {}: {}", path, show(&entry.new))),
+            DiffKind::Removed => paint(RED, format!("- {}: {}", path, show(&entry.old))),
+            DiffKind::Changed => paint(YELLOW, format!("~ {}: {} -> {}", path, show(&entry.old), show(&entry.new))),
+            DiffKind::Reordered => paint(CYAN, format!("* {}: keys {} -> {}", path, show(&entry.old), show(&entry.new))),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::{diff_json, diff_to_json, render_diff, DiffEntry, DiffKind, DiffOptions};
+    use crate::value::JsonValue;
+
+    #[test]
+    fn test_diff_json() {
+        let a = r#"{"a":1,"b":{"c":[1,2,3],"d":"x"},"e/f":true}"#;
+        let b = r#"{"a":1.0,"b":{"c":[1,5],"d":"x","g":null},"e/f":false}"#;
+        let entries = diff_json(a, b, &DiffOptions::default()).unwrap();
+        assert_eq!(entries, vec![
+            DiffEntry { kind: DiffKind::Changed, path: String::from("/b/c/1"), old: Some(JsonValue::from(2i64)), new: Some(JsonValue::from(5i64)) },
+            DiffEntry { kind: DiffKind::Removed, path: String::from("/b/c/2"), old: Some(JsonValue::from(3i64)), new: None },
+            DiffEntry { kind: DiffKind::Added, path: String::from("/b/g"), old: None, new: Some(JsonValue::Null) },
+            DiffEntry { kind: DiffKind::Changed, path: String::from("/e~1f"), old: Some(JsonValue::Bool(true)), new: Some(JsonValue::Bool(false)) },
+        ]);
+        assert_eq!(render_diff(&entries, false),
+            "~ /b/c/1: 2 -> 5\n- /b/c/2: 3\n+ /b/g: null\n~ /e~1f: true -> false\n");
+        assert_eq!(diff_to_json(&entries[1..3]).to_json_string(),
+            r#"[{"kind":"removed","path":"/b/c/2","old":3},{"kind":"added","path":"/b/g","new":null}]"#);
+    }
+
+    #[test]
+    fn test_diff_key_order() {
+        let a = r#"{"a":1,"b":2}"#;
+        let b = r#"{"b":2,"a":1}"#;
+        let entries = diff_json(a, b, &DiffOptions::default()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, DiffKind::Reordered);
+        assert_eq!(render_diff(&entries, false), "* (root): keys [\"a\",\"b\"] -> [\"b\",\"a\"]\n");
+        let ignoring = DiffOptions { ignore_key_order: true };
+        assert!(diff_json(a, b, &ignoring).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_diff_pretty_fixture_is_equal() {
+        for name in ["long_test", "noaa_climate_test", "massive_arr_test"] {
+            let original = fs::read_to_string(format!("testfiles/{}.json", name)).unwrap();
+            let pretty = fs::read_to_string(format!("testfiles/{}-pretty.json", name)).unwrap();
+            assert!(diff_json(&original, &pretty, &DiffOptions::default()).unwrap().is_empty(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::fmt;
+
+use crate::position::Span;
+
+/// A problem found in a JSON document, with the region of the input it is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    pub message: String,
+    pub span: Span,
+    /// A suggestion for fixing the problem, if there is an obvious one.
+    pub help: Option<String>,
+}
+
+impl JsonError {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        JsonError { message: message.into(), span, help: None }
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.span.start)?;
+        match &self.help {
+            Some(help) => write!(f, "; {}", help),
+            None => Ok(()),
+        }
+    }
+}
+
+// Most of the crate reports errors as plain strings; the position and hint go along.
+impl From<JsonError> for String {
+    fn from(error: JsonError) -> String {
+        error.to_string()
+    }
+}
@@ -0,0 +1,191 @@
+use std::ops::Range;
+
+use crate::parser::parse_json;
+use crate::position::Span;
+use crate::{prettify_json_with, FormatConfig, JSONLexer, Token, IGNORE_WS};
+
+/// Replace the text in `span` with `new_text`. Spans are byte offsets into the
+/// text the edit was computed for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub new_text: String,
+}
+
+/// Applies non-overlapping edits, in any order, to `input`.
+pub fn apply_edits(input: &str, edits: &[TextEdit]) -> String {
+    let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+    sorted.sort_by_key(|edit| edit.span.start);
+    let mut out = String::with_capacity(input.len());
+    let mut copied = 0;
+    for edit in sorted {
+        out.push_str(&input[copied..edit.span.start]);
+        out.push_str(&edit.new_text);
+        copied = edit.span.end;
+    }
+    out.push_str(&input[copied..]);
+    out
+}
+
+/// Prettifies the part of `input` that `range` selects, leaving the rest alone.
+/// The selection grows to the smallest complete value around it, or to the run
+/// of members or elements of one container it touches when each of those starts
+/// its own line; that text is laid out like `prettify_json` would, indented from
+/// the line it starts on. Only whitespace between tokens is edited, unless the
+/// config reorders keys, in which case the whole region is replaced.
+pub fn format_range(input: &str, range: Range<usize>, config: &FormatConfig) -> Result<Vec<TextEdit>, String> {
+    parse_json(input)?;
+    let mut lexer = JSONLexer::from(String::from(input), IGNORE_WS);
+    lexer.lex()?;
+    let spans = &lexer.spans;
+    let root = Node::build(&lexer.lexed_input, &mut 0);
+    let text = |first: usize, last: usize| &input[spans[first].start..spans[last].end];
+
+    let (first, last, formatted) = match select(&root, &range, input, spans) {
+        Selection::Value(node) => {
+            let indent = line_indent(input, spans[node.first].start);
+            (node.first, node.last, indented(&prettify_json_with(String::from(text(node.first, node.last)), config)?, indent))
+        },
+        Selection::Items(items) => {
+            let indent = line_indent(input, spans[items[0].start].start);
+            let mut parts = vec![];
+            for item in items {
+                let value = prettify_json_with(String::from(text(item.value.first, item.value.last)), config)?;
+                let key = if item.start < item.value.first { format!("{}:", text(item.start, item.start)) } else { String::new() };
+                parts.push(format!("{}{}", key, indented(&value, indent)));
+            }
+            (items[0].start, items[items.len() - 1].value.last, parts.join(&format!(",\n{}", indent)))
+        },
+    };
+
+    let mut formatted_lexer = JSONLexer::from(formatted.clone(), IGNORE_WS);
+    formatted_lexer.lex()?;
+    let new_tokens = &formatted_lexer.lexed_input[..formatted_lexer.lexed_input.len() - 1];
+    if *new_tokens != lexer.lexed_input[first..=last] {
+        let span = Span::new(spans[first].start, spans[last].end);
+        return Ok(if input[span.start..span.end] == formatted { vec![] } else { vec![TextEdit { span, new_text: formatted }] });
+    }
+    let new_spans = &formatted_lexer.spans;
+    let mut edits = vec![];
+    for i in 0..last - first {
+        let old_gap = Span::new(spans[first + i].end, spans[first + i + 1].start);
+        let new_gap = &formatted[new_spans[i].end..new_spans[i + 1].start];
+        if input[old_gap.start..old_gap.end] != *new_gap {
+            edits.push(TextEdit { span: old_gap, new_text: String::from(new_gap) });
+        }
+    }
+    Ok(edits)
+}
+
+// A value as token indices: a scalar is one token, a container runs from its
+// opening to its closing bracket.
+struct Node {
+    first: usize,
+    last: usize,
+    items: Vec<Item>,
+}
+
+// A member or element; `start` is the key for members and the value otherwise.
+struct Item {
+    start: usize,
+    value: Node,
+}
+
+impl Node {
+    // the tokens are known to be a valid document
+    fn build(tokens: &[Token], pos: &mut usize) -> Node {
+        let first = *pos;
+        *pos += 1;
+        let is_object = matches!(tokens[first], Token::OpenBrace(_));
+        if !is_object && !matches!(tokens[first], Token::OpenBrack(_)) {
+            return Node { first, last: first, items: vec![] };
+        }
+        let mut items = vec![];
+        while !matches!(tokens[*pos], Token::CloseBrace(_) | Token::CloseBrack(_)) {
+            let start = *pos;
+            if is_object {
+                *pos += 2; // the key and the colon
+            }
+            items.push(Item { start, value: Node::build(tokens, pos) });
+            if matches!(tokens[*pos], Token::Comma(_)) {
+                *pos += 1;
+            }
+        }
+        *pos += 1;
+        Node { first, last: *pos - 1, items }
+    }
+}
+
+enum Selection<'a> {
+    Value(&'a Node),
+    Items(&'a [Item]),
+}
+
+fn select<'a>(node: &'a Node, range: &Range<usize>, input: &str, spans: &[Span]) -> Selection<'a> {
+    // an empty range still selects the value it is in
+    let end = range.end.max(range.start + 1);
+    let touched: Vec<usize> = (0..node.items.len())
+        .filter(|&i| spans[node.items[i].start].start < end && range.start < spans[node.items[i].value.last].end)
+        .collect();
+    let (Some(&from), Some(&to)) = (touched.first(), touched.last()) else { return Selection::Value(node) };
+    let item = &node.items[from];
+    if from == to && spans[item.value.first].start <= range.start && range.end <= spans[item.value.last].end {
+        return select(&item.value, range, input, spans);
+    }
+    let item_start = spans[item.start].start;
+    if input[..item_start].rsplit('\n').next().is_some_and(|before| before.trim().is_empty()) {
+        Selection::Items(&node.items[from..=to])
+    } else {
+        Selection::Value(node)
+    }
+}
+
+// the whitespace at the start of the line `offset` is on
+fn line_indent(input: &str, offset: usize) -> &str {
+    let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &input[line_start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+fn indented(text: &str, indent: &str) -> String {
+    text.replace('\n', &format!("\n{}", indent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_edits, format_range, TextEdit};
+    use crate::position::Span;
+    use crate::{FormatConfig, KeyOrder};
+
+    fn formatted(input: &str, selected: &str) -> String {
+        let start = input.find(selected).unwrap();
+        let edits = format_range(input, start..start + selected.len(), &FormatConfig::default()).unwrap();
+        apply_edits(input, &edits)
+    }
+
+    #[test]
+    fn test_format_value_in_range() {
+        let input = "{\n \"a\":{\"b\":[1,2],\n  \"c\":  true},\n \"d\":[3,   4]\n}";
+        assert_eq!(formatted(input, "[1,2]"), "{\n \"a\":{\"b\":[\n  1,\n  2\n ],\n  \"c\":  true},\n \"d\":[3,   4]\n}");
+        // touching two members of a one-line object formats the whole object
+        assert_eq!(formatted(input, "2],\n  \"c\""), "{\n \"a\":{\n  \"b\":[\n   1,\n   2\n  ],\n  \"c\":true\n },\n \"d\":[3,   4]\n}");
+        assert_eq!(formatted(input, "4"), input);
+    }
+
+    #[test]
+    fn test_format_member_run() {
+        let input = "{\n  \"a\":  1,\n  \"b\" :[2,3],\n  \"c\": {}\n}";
+        assert_eq!(formatted(input, "1,\n  \"b\""), "{\n  \"a\":1,\n  \"b\":[\n   2,\n   3\n  ],\n  \"c\": {}\n}");
+        let edits = format_range(input, 8..9, &FormatConfig::default()).unwrap();
+        assert_eq!(edits, [TextEdit { span: Span::new(8, 10), new_text: String::new() }]);
+    }
+
+    #[test]
+    fn test_format_range_sorting_and_errors() {
+        let input = "[{\"b\":1,\"a\":2}, 3]";
+        let config = FormatConfig { sort_keys: Some(KeyOrder::Lexicographic), ..Default::default() };
+        let edits = format_range(input, 2..3, &config).unwrap();
+        assert_eq!(apply_edits(input, &edits), "[{\n \"a\":2,\n \"b\":1\n}, 3]");
+        assert!(format_range("[1,", 0..1, &FormatConfig::default()).is_err());
+    }
+}
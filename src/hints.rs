@@ -0,0 +1,112 @@
+/// The JSON literal a word from another language stands for, e.g. `true` for
+/// `True` and `null` for `None`. NaN and the infinities have no JSON form, so
+/// they stand for `null` too.
+pub fn foreign_literal(word: &str) -> Option<&'static str> {
+    if is_non_finite(word) {
+        return Some("null");
+    }
+    match word {
+        "True" | "TRUE" => Some("true"),
+        "False" | "FALSE" => Some("false"),
+        "None" | "NULL" | "Null" | "nil" | "undefined" => Some("null"),
+        _ => None,
+    }
+}
+
+fn is_non_finite(word: &str) -> bool {
+    matches!(word, "NaN" | "nan" | "Infinity" | "-Infinity" | "inf" | "-inf")
+}
+
+/// A hint for a bare word where a value was expected, e.g. `True` or `None`.
+pub fn word_hint(word: &str) -> Option<String> {
+    if is_non_finite(word) {
+        return Some(String::from("JSON numbers cannot be NaN or Infinity; use null or a string such as \"NaN\""));
+    }
+    let Some(literal) = foreign_literal(word) else {
+        return closest_literal(word).map(|literal| format!("did you mean `{}`?", literal));
+    };
+    let reason = match literal {
+        "null" => format!("JSON has no `{}`", word),
+        _ => String::from("JSON booleans are lowercase"),
+    };
+    Some(format!("{}: did you mean `{}`?", reason, literal))
+}
+
+// true, false or null if `word` is one typo away from it
+fn closest_literal(word: &str) -> Option<&'static str> {
+    let word = word.to_lowercase();
+    ["true", "false", "null"].into_iter().find(|literal| edit_distance(&word, literal) <= 1)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (diagonal + usize::from(ca != *cb)).min(row[j] + 1).min(above + 1);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// A hint for text that starts like a number but isn't a valid JSON number.
+pub fn number_hint(raw: &str) -> Option<String> {
+    let unsigned = raw.strip_prefix('-').unwrap_or(raw);
+    if unsigned.starts_with("0x") || unsigned.starts_with("0X") {
+        return Some(String::from("JSON has no hexadecimal numbers; write the value in decimal"));
+    }
+    if unsigned.len() > 1 && unsigned.starts_with('0') && unsigned.as_bytes()[1].is_ascii_digit() {
+        let trimmed = unsigned.trim_start_matches('0');
+        let fixed = if trimmed.is_empty() || trimmed.starts_with('.') { format!("0{}", trimmed) } else { String::from(trimmed) };
+        let sign = if raw.starts_with('-') { "-" } else { "" };
+        return Some(format!("numbers cannot have leading zeros: did you mean `{}{}`?", sign, fixed));
+    }
+    if unsigned.starts_with('.') {
+        return Some(format!("a fraction needs a digit before the point: did you mean `{}`?", raw.replacen('.', "0.", 1)));
+    }
+    if unsigned.ends_with('.') {
+        return Some(format!("a fraction needs a digit after the point: did you mean `{}0`?", raw));
+    }
+    None
+}
+
+/// A hint for a string in quotes JSON doesn't use.
+pub fn quote_hint(quote: char, text: &str) -> String {
+    match quote {
+        '\'' => format!("JSON strings use double quotes: \"{}\"", text),
+        _ => String::from("replace the typographic quotes with plain ASCII '\"'"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{number_hint, quote_hint, word_hint};
+
+    #[test]
+    fn test_word_hints() {
+        assert_eq!(word_hint("True").unwrap(), "JSON booleans are lowercase: did you mean `true`?");
+        assert_eq!(word_hint("None").unwrap(), "JSON has no `None`: did you mean `null`?");
+        assert_eq!(word_hint("NULL").unwrap(), "JSON has no `NULL`: did you mean `null`?");
+        assert!(word_hint("NaN").unwrap().contains("NaN or Infinity"));
+        assert!(word_hint("-Infinity").unwrap().contains("NaN or Infinity"));
+        assert_eq!(word_hint("tru").unwrap(), "did you mean `true`?");
+        assert_eq!(word_hint("flase"), None);
+        assert_eq!(word_hint("nul").unwrap(), "did you mean `null`?");
+        assert_eq!(word_hint("hello"), None);
+    }
+
+    #[test]
+    fn test_number_and_quote_hints() {
+        assert_eq!(number_hint("007").unwrap(), "numbers cannot have leading zeros: did you mean `7`?");
+        assert_eq!(number_hint("-00.5").unwrap(), "numbers cannot have leading zeros: did you mean `-0.5`?");
+        assert_eq!(number_hint(".5").unwrap(), "a fraction needs a digit before the point: did you mean `0.5`?");
+        assert_eq!(number_hint("-1.").unwrap(), "a fraction needs a digit after the point: did you mean `-1.0`?");
+        assert!(number_hint("0x1F").unwrap().contains("hexadecimal"));
+        assert_eq!(number_hint("1e"), None);
+        assert_eq!(quote_hint('\'', "a"), "JSON strings use double quotes: \"a\"");
+    }
+}
@@ -0,0 +1,411 @@
+/// A regular expression in the interoperable subset of RFC 9485 (I-Regexp), as
+/// used by the JSONPath `match` and `search` functions. It compiles to an NFA
+/// that is run in lockstep, so matching is linear in the input whatever the
+/// pattern.
+///
+/// Unicode property escapes (`\p{..}` and `\P{..}`) are part of I-Regexp but
+/// are not supported: there are no character category tables to check them
+/// against, so patterns using them fail to compile, and `match` and `search`
+/// with such a pattern are false for every value.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Vec<Inst>,
+}
+
+#[derive(Debug, Clone)]
+struct CharClass {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    fn single(c: char) -> Self {
+        CharClass { negated: false, ranges: vec![(c, c)] }
+    }
+
+    // `.` is any character but a line break
+    fn any() -> Self {
+        CharClass { negated: true, ranges: vec![('\n', '\n'), ('\r', '\r')] }
+    }
+
+    fn contains(&self, c: char) -> bool {
+        self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != self.negated
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Class(CharClass),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat { node: Box<Node>, min: u32, max: Option<u32> },
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(CharClass),
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+// bounds on `{n,m}` and on the compiled program, since nested repeats
+// multiply, so a pattern cannot blow up memory or matching time
+const MAX_REPEAT: u32 = 1000;
+const MAX_PROGRAM: usize = 20_000;
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut parser = RegexParser { chars: pattern.chars().collect(), pos: 0 };
+        let node = parser.parse_alt()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unmatched ')'"));
+        }
+        if program_size(&node) > MAX_PROGRAM {
+            return Err(format!("Invalid regular expression: the pattern is too large (more than {} instructions)", MAX_PROGRAM));
+        }
+        let mut program = vec![];
+        compile(&node, &mut program);
+        program.push(Inst::Match);
+        Ok(Regex { program })
+    }
+
+    /// Whether the whole of `text` matches.
+    pub fn full_match(&self, text: &str) -> bool {
+        self.run(text, true)
+    }
+
+    /// Whether some substring of `text` matches.
+    pub fn search(&self, text: &str) -> bool {
+        self.run(text, false)
+    }
+
+    fn run(&self, text: &str, anchored: bool) -> bool {
+        let mut current = vec![];
+        let mut next = vec![];
+        let mut seen = vec![usize::MAX; self.program.len()];
+        let mut step = 0;
+        self.add_thread(&mut current, &mut seen, step, 0);
+        for c in text.chars() {
+            if !anchored && current.iter().any(|&pc| matches!(self.program[pc], Inst::Match)) {
+                return true;
+            }
+            step += 1;
+            next.clear();
+            for &pc in &current {
+                if let Inst::Char(class) = &self.program[pc] {
+                    if class.contains(c) {
+                        self.add_thread(&mut next, &mut seen, step, pc + 1);
+                    }
+                }
+            }
+            if !anchored {
+                self.add_thread(&mut next, &mut seen, step, 0);
+            }
+            std::mem::swap(&mut current, &mut next);
+        }
+        current.iter().any(|&pc| matches!(self.program[pc], Inst::Match))
+    }
+
+    // follows jumps and splits, recording each instruction once per step
+    fn add_thread(&self, threads: &mut Vec<usize>, seen: &mut [usize], step: usize, pc: usize) {
+        if seen[pc] == step {
+            return;
+        }
+        seen[pc] = step;
+        match self.program[pc] {
+            Inst::Jump(to) => self.add_thread(threads, seen, step, to),
+            Inst::Split(a, b) => {
+                self.add_thread(threads, seen, step, a);
+                self.add_thread(threads, seen, step, b);
+            },
+            _ => threads.push(pc),
+        }
+    }
+}
+
+// how many instructions `compile` emits for `node`, saturating on overflow
+fn program_size(node: &Node) -> usize {
+    match node {
+        Node::Empty => 0,
+        Node::Class(_) => 1,
+        Node::Concat(nodes) => nodes.iter().fold(0, |size, node| size.saturating_add(program_size(node))),
+        Node::Alt(branches) => branches.iter().fold(2 * (branches.len() - 1), |size, node| size.saturating_add(program_size(node))),
+        Node::Repeat { node, min, max } => {
+            let size = program_size(node);
+            let optional = match max {
+                None => size.saturating_add(2),
+                Some(max) => ((max - min) as usize).saturating_mul(size.saturating_add(1)),
+            };
+            (*min as usize).saturating_mul(size).saturating_add(optional)
+        },
+    }
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Empty => (),
+        Node::Class(class) => program.push(Inst::Char(class.clone())),
+        Node::Concat(nodes) => nodes.iter().for_each(|node| compile(node, program)),
+        Node::Alt(branches) => {
+            let mut jumps = vec![];
+            for (i, branch) in branches.iter().enumerate() {
+                if i + 1 < branches.len() {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(branch, program);
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    let after = program.len();
+                    program[split] = Inst::Split(split + 1, after);
+                } else {
+                    compile(branch, program);
+                }
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        },
+        Node::Repeat { node, min, max } => {
+            for _ in 0..*min {
+                compile(node, program);
+            }
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(node, program);
+                    program.push(Inst::Jump(split));
+                    let end = program.len();
+                    program[split] = Inst::Split(split + 1, end);
+                },
+                Some(max) => {
+                    let mut splits = vec![];
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        compile(node, program);
+                    }
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, end);
+                    }
+                },
+            }
+        },
+    }
+}
+
+struct RegexParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl RegexParser {
+    fn error(&self, msg: &str) -> String {
+        format!("Invalid regular expression at position {}: {}", self.pos, msg)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn parse_alt(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.parse_branch()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            branches.push(self.parse_branch()?);
+        }
+        Ok(if branches.len() == 1 { branches.pop().unwrap() } else { Node::Alt(branches) })
+    }
+
+    fn parse_branch(&mut self) -> Result<Node, String> {
+        let mut pieces = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            pieces.push(self.parse_quantifier(atom)?);
+        }
+        Ok(match pieces.len() {
+            0 => Node::Empty,
+            1 => pieces.pop().unwrap(),
+            _ => Node::Concat(pieces),
+        })
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        let c = self.peek().ok_or_else(|| self.error("expected an atom"))?;
+        self.pos += 1;
+        match c {
+            '(' => {
+                let inner = self.parse_alt()?;
+                if self.peek() != Some(')') {
+                    return Err(self.error("missing ')'"));
+                }
+                self.pos += 1;
+                Ok(inner)
+            },
+            '[' => self.parse_class().map(Node::Class),
+            '.' => Ok(Node::Class(CharClass::any())),
+            '\\' => self.parse_escape().map(|c| Node::Class(CharClass::single(c))),
+            '?' | '*' | '+' | '{' | '}' | ']' => {
+                self.pos -= 1;
+                Err(self.error(&format!("unexpected '{}'", c)))
+            },
+            c => Ok(Node::Class(CharClass::single(c))),
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or_else(|| self.error("dangling '\\'"))?;
+        self.pos += 1;
+        match c {
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            '\\' | '|' | '.' | '-' | '^' | '?' | '*' | '+' | '{' | '}' | '(' | ')' | '[' | ']' => Ok(c),
+            'p' | 'P' => Err(self.error("Unicode property escapes are not supported")),
+            _ => Err(self.error(&format!("unknown escape '\\{}'", c))),
+        }
+    }
+
+    fn parse_class(&mut self) -> Result<CharClass, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut ranges = vec![];
+        let mut first = true;
+        loop {
+            let c = match self.peek() {
+                Some(']') if !first => {
+                    self.pos += 1;
+                    break;
+                },
+                Some(c) => c,
+                None => return Err(self.error("missing ']'")),
+            };
+            first = false;
+            self.pos += 1;
+            let lo = match c {
+                '\\' => self.parse_escape()?,
+                '[' => return Err(self.error("'[' must be escaped inside a class")),
+                c => c,
+            };
+            // a '-' right before ']' is literal
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&c| c != ']') {
+                self.pos += 1;
+                let hi = match self.peek() {
+                    Some('\\') => {
+                        self.pos += 1;
+                        self.parse_escape()?
+                    },
+                    Some(c) => {
+                        self.pos += 1;
+                        c
+                    },
+                    None => return Err(self.error("missing ']'")),
+                };
+                if hi < lo {
+                    return Err(self.error(&format!("range '{}-{}' is out of order", lo, hi)));
+                }
+                ranges.push((lo, hi));
+            } else {
+                ranges.push((lo, lo));
+            }
+        }
+        Ok(CharClass { negated, ranges })
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('?') => (0, Some(1)),
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.parse_count()?;
+                let max = if self.peek() == Some(',') {
+                    self.pos += 1;
+                    if self.peek() == Some('}') { None } else { Some(self.parse_count()?) }
+                } else {
+                    Some(min)
+                };
+                if self.peek() != Some('}') {
+                    return Err(self.error("missing '}'"));
+                }
+                if max.is_some_and(|max| max < min) {
+                    return Err(self.error("repeat bounds are out of order"));
+                }
+                (min, max)
+            },
+            _ => return Ok(atom),
+        };
+        self.pos += 1;
+        Ok(Node::Repeat { node: Box::new(atom), min, max })
+    }
+
+    fn parse_count(&mut self) -> Result<u32, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        match digits.parse::<u32>() {
+            Ok(n) if n <= MAX_REPEAT => Ok(n),
+            Ok(_) => Err(self.error(&format!("repeat count may be at most {}", MAX_REPEAT))),
+            Err(_) => Err(self.error("expected a repeat count")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{program_size, Regex, RegexParser};
+
+    #[test]
+    fn test_full_match_and_search() {
+        let re = Regex::new("a(b|cd)*e?").unwrap();
+        assert!(re.full_match("a"));
+        assert!(re.full_match("abcdbe"));
+        assert!(!re.full_match("abx"));
+        assert!(re.search("xxabx"));
+        assert!(!re.search("xyz"));
+
+        let re = Regex::new("[0-9]{2,3}-[^a-z.]+").unwrap();
+        assert!(re.full_match("123-ABC"));
+        assert!(!re.full_match("1-ABC"));
+        assert!(!re.full_match("12-a"));
+        assert!(Regex::new("a.c").unwrap().full_match("a\u{e9}c"));
+        assert!(!Regex::new("a.c").unwrap().full_match("a\nc"));
+        assert!(Regex::new(r"\.\*[a\-]").unwrap().full_match(".*-"));
+        assert!(Regex::new("").unwrap().full_match(""));
+    }
+
+    #[test]
+    fn test_pathological_pattern_is_linear() {
+        let re = Regex::new("(a*)*b").unwrap();
+        assert!(!re.full_match(&"a".repeat(10_000)));
+    }
+
+    #[test]
+    fn test_program_size_is_bounded() {
+        let pattern = "(a|bc){3,5}d*|e?";
+        let node = RegexParser { chars: pattern.chars().collect(), pos: 0 }.parse_alt().unwrap();
+        assert_eq!(program_size(&node), Regex::new(pattern).unwrap().program.len() - 1);
+        assert!(Regex::new("(a{1000}){20}").is_ok());
+        let err = Regex::new("((a{1000}){1000}){1000}").unwrap_err();
+        assert!(err.contains("too large"), "{}", err);
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        for pattern in ["(a", "a)", "[a", "*a", "a{3,1}", r"\p{L}", "[z-a]", r"\d"] {
+            assert!(Regex::new(pattern).is_err(), "{}", pattern);
+        }
+    }
+}
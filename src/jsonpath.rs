@@ -0,0 +1,890 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt::Write;
+
+use crate::iregexp::Regex;
+use crate::number::JsonNumber;
+use crate::value::JsonValue;
+
+/// A parsed JSONPath query (RFC 9535), e.g. `$.data[?@.anomaly < 0]`.
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    query: Query,
+}
+
+/// One node selected by a query: the value and its normalized path, such as
+/// `$['data']['189512']`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathMatch<'a> {
+    pub path: String,
+    pub value: &'a JsonValue,
+}
+
+#[derive(Debug, Clone)]
+struct Query {
+    relative: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice { start: Option<i64>, end: Option<i64>, step: Option<i64> },
+    Filter(LogicalExpr),
+}
+
+#[derive(Debug, Clone)]
+enum LogicalExpr {
+    Or(Vec<LogicalExpr>),
+    And(Vec<LogicalExpr>),
+    Not(Box<LogicalExpr>),
+    Compare(Comparable, CompareOp, Comparable),
+    Exists(Query),
+    Function(FunctionExpr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Comparable {
+    Literal(JsonValue),
+    Query(Query),
+    Function(FunctionExpr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParamType {
+    Value,
+    Nodes,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "length" => Some(Function::Length),
+            "count" => Some(Function::Count),
+            "match" => Some(Function::Match),
+            "search" => Some(Function::Search),
+            "value" => Some(Function::Value),
+            _ => None,
+        }
+    }
+
+    fn params(&self) -> &'static [ParamType] {
+        match self {
+            Function::Length => &[ParamType::Value],
+            Function::Count | Function::Value => &[ParamType::Nodes],
+            Function::Match | Function::Search => &[ParamType::Value, ParamType::Value],
+        }
+    }
+
+    // match and search give a LogicalType, the rest a ValueType
+    fn is_logical(&self) -> bool {
+        matches!(self, Function::Match | Function::Search)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FunctionExpr {
+    function: Function,
+    args: Vec<Comparable>,
+    // compiled up front when the pattern is a string literal
+    regex: Option<Regex>,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let mut parser = PathParser { src: path, pos: 0 };
+        if !parser.eat('$') {
+            return Err(parser.error("a query must start with '$'"));
+        }
+        let segments = parser.parse_segments()?;
+        if parser.pos < path.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+        Ok(JsonPath { query: Query { relative: false, segments } })
+    }
+
+    /// The nodes the query selects from `root`, in document order.
+    pub fn query<'a>(&self, root: &'a JsonValue) -> Vec<PathMatch<'a>> {
+        let start = vec![PathMatch { path: String::from("$"), value: root }];
+        select(&self.query.segments, start, root, true)
+    }
+}
+
+/// Parses `path` and runs it against `root`.
+pub fn query_json_path<'a>(root: &'a JsonValue, path: &str) -> Result<Vec<PathMatch<'a>>, String> {
+    Ok(JsonPath::parse(path)?.query(root))
+}
+
+/// Writes a member name the way normalized paths do: `['name']`, escaping only
+/// what must be escaped.
+fn push_name_segment(path: &mut String, name: &str) {
+    path.push_str("['");
+    for c in name.chars() {
+        match c {
+            '\'' => path.push_str("\\'"),
+            '\\' => path.push_str("\\\\"),
+            '\u{8}' => path.push_str("\\b"),
+            '\u{c}' => path.push_str("\\f"),
+            '\n' => path.push_str("\\n"),
+            '\r' => path.push_str("\\r"),
+            '\t' => path.push_str("\\t"),
+            c if c < ' ' => { let _ = write!(path, "\\u{:04x}", c as u32); },
+            c => path.push(c),
+        }
+    }
+    path.push_str("']");
+}
+
+// Paths are only built when `track` is set; filter subqueries don't need them.
+fn child<'a>(parent: &PathMatch<'a>, name: &str, value: &'a JsonValue, track: bool) -> PathMatch<'a> {
+    let mut path = String::new();
+    if track {
+        path.push_str(&parent.path);
+        push_name_segment(&mut path, name);
+    }
+    PathMatch { path, value }
+}
+
+fn element<'a>(parent: &PathMatch<'a>, index: usize, value: &'a JsonValue, track: bool) -> PathMatch<'a> {
+    let path = if track { format!("{}[{}]", parent.path, index) } else { String::new() };
+    PathMatch { path, value }
+}
+
+fn select<'a>(segments: &[Segment], mut nodes: Vec<PathMatch<'a>>, root: &'a JsonValue, track: bool) -> Vec<PathMatch<'a>> {
+    for segment in segments {
+        let mut out = vec![];
+        for node in &nodes {
+            match segment {
+                Segment::Child(selectors) => apply_selectors(selectors, node, root, track, &mut out),
+                Segment::Descendant(selectors) => visit_descendants(selectors, node, root, track, &mut out),
+            }
+        }
+        nodes = out;
+    }
+    nodes
+}
+
+// the node itself first, then each child's subtree in order
+fn visit_descendants<'a>(selectors: &[Selector], node: &PathMatch<'a>, root: &'a JsonValue, track: bool, out: &mut Vec<PathMatch<'a>>) {
+    apply_selectors(selectors, node, root, track, out);
+    match node.value {
+        JsonValue::Object(obj) => {
+            for (key, value) in obj.iter() {
+                visit_descendants(selectors, &child(node, key, value, track), root, track, out);
+            }
+        },
+        JsonValue::Array(arr) => {
+            for (i, value) in arr.iter().enumerate() {
+                visit_descendants(selectors, &element(node, i, value, track), root, track, out);
+            }
+        },
+        _ => (),
+    }
+}
+
+fn apply_selectors<'a>(selectors: &[Selector], node: &PathMatch<'a>, root: &'a JsonValue, track: bool, out: &mut Vec<PathMatch<'a>>) {
+    for selector in selectors {
+        match (selector, node.value) {
+            (Selector::Name(name), JsonValue::Object(obj)) => {
+                if let Some(value) = obj.get(name) {
+                    out.push(child(node, name, value, track));
+                }
+            },
+            (Selector::Wildcard, JsonValue::Object(obj)) => {
+                out.extend(obj.iter().map(|(key, value)| child(node, key, value, track)));
+            },
+            (Selector::Wildcard, JsonValue::Array(arr)) => {
+                out.extend(arr.iter().enumerate().map(|(i, value)| element(node, i, value, track)));
+            },
+            (Selector::Index(index), JsonValue::Array(arr)) => {
+                let i = if *index < 0 { arr.len() as i64 + index } else { *index };
+                if 0 <= i && (i as usize) < arr.len() {
+                    out.push(element(node, i as usize, &arr[i as usize], track));
+                }
+            },
+            (Selector::Slice { start, end, step }, JsonValue::Array(arr)) => {
+                for i in slice_indices(arr.len() as i64, *start, *end, step.unwrap_or(1)) {
+                    out.push(element(node, i, &arr[i], track));
+                }
+            },
+            (Selector::Filter(expr), JsonValue::Object(obj)) => {
+                for (key, value) in obj.iter() {
+                    if test_filter(expr, value, root) {
+                        out.push(child(node, key, value, track));
+                    }
+                }
+            },
+            (Selector::Filter(expr), JsonValue::Array(arr)) => {
+                for (i, value) in arr.iter().enumerate() {
+                    if test_filter(expr, value, root) {
+                        out.push(element(node, i, value, track));
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+}
+
+/// The indices `[start:end:step]` selects from an array of `len` elements
+/// (RFC 9535, section 2.3.4.2.2).
+fn slice_indices(len: i64, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+    let mut indices = vec![];
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            indices.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+        let lower = end.map_or(-1, normalize).clamp(-1, len - 1);
+        let mut i = upper;
+        while lower < i {
+            indices.push(i as usize);
+            i += step;
+        }
+    }
+    indices
+}
+
+fn test_filter(expr: &LogicalExpr, current: &JsonValue, root: &JsonValue) -> bool {
+    match expr {
+        LogicalExpr::Or(exprs) => exprs.iter().any(|expr| test_filter(expr, current, root)),
+        LogicalExpr::And(exprs) => exprs.iter().all(|expr| test_filter(expr, current, root)),
+        LogicalExpr::Not(expr) => !test_filter(expr, current, root),
+        LogicalExpr::Compare(left, op, right) => {
+            let left = eval_comparable(left, current, root);
+            let right = eval_comparable(right, current, root);
+            compare(left.as_deref(), *op, right.as_deref())
+        },
+        LogicalExpr::Exists(query) => !eval_query(query, current, root).is_empty(),
+        LogicalExpr::Function(function) => matches!(eval_function(function, current, root), Evaluated::Logical(true)),
+    }
+}
+
+fn eval_query<'a>(query: &Query, current: &'a JsonValue, root: &'a JsonValue) -> Vec<PathMatch<'a>> {
+    let start = if query.relative { current } else { root };
+    select(&query.segments, vec![PathMatch { path: String::new(), value: start }], root, false)
+}
+
+enum Evaluated<'a> {
+    Value(Option<Cow<'a, JsonValue>>),
+    Logical(bool),
+}
+
+// `None` is the special result "Nothing" of RFC 9535
+fn eval_comparable<'a>(comparable: &'a Comparable, current: &'a JsonValue, root: &'a JsonValue) -> Option<Cow<'a, JsonValue>> {
+    match comparable {
+        Comparable::Literal(value) => Some(Cow::Borrowed(value)),
+        Comparable::Query(query) => eval_query(query, current, root).into_iter().next().map(|node| Cow::Borrowed(node.value)),
+        Comparable::Function(function) => match eval_function(function, current, root) {
+            Evaluated::Value(value) => value,
+            Evaluated::Logical(_) => None,
+        },
+    }
+}
+
+fn eval_nodes<'a>(arg: &'a Comparable, current: &'a JsonValue, root: &'a JsonValue) -> Vec<PathMatch<'a>> {
+    match arg {
+        Comparable::Query(query) => eval_query(query, current, root),
+        _ => vec![],
+    }
+}
+
+fn eval_function<'a>(function: &'a FunctionExpr, current: &'a JsonValue, root: &'a JsonValue) -> Evaluated<'a> {
+    let args = &function.args;
+    match function.function {
+        Function::Length => {
+            let length = match eval_comparable(&args[0], current, root).as_deref() {
+                Some(JsonValue::String(s)) => Some(s.chars().count()),
+                Some(JsonValue::Array(arr)) => Some(arr.len()),
+                Some(JsonValue::Object(obj)) => Some(obj.len()),
+                _ => None,
+            };
+            Evaluated::Value(length.map(|n| Cow::Owned(JsonValue::from(n as u64))))
+        },
+        Function::Count => {
+            let count = eval_nodes(&args[0], current, root).len();
+            Evaluated::Value(Some(Cow::Owned(JsonValue::from(count as u64))))
+        },
+        Function::Value => {
+            let nodes = eval_nodes(&args[0], current, root);
+            let value = if nodes.len() == 1 { Some(Cow::Borrowed(nodes[0].value)) } else { None };
+            Evaluated::Value(value)
+        },
+        Function::Match | Function::Search => {
+            let text = eval_comparable(&args[0], current, root);
+            let pattern = eval_comparable(&args[1], current, root);
+            let (Some(JsonValue::String(text)), Some(JsonValue::String(pattern))) = (text.as_deref(), pattern.as_deref()) else {
+                return Evaluated::Logical(false);
+            };
+            let compiled;
+            let regex = match &function.regex {
+                Some(regex) => regex,
+                None => match Regex::new(pattern) {
+                    Ok(regex) => { compiled = regex; &compiled },
+                    Err(_) => return Evaluated::Logical(false),
+                },
+            };
+            let found = if function.function == Function::Match { regex.full_match(text) } else { regex.search(text) };
+            Evaluated::Logical(found)
+        },
+    }
+}
+
+fn compare(left: Option<&JsonValue>, op: CompareOp, right: Option<&JsonValue>) -> bool {
+    match op {
+        CompareOp::Eq => left == right,
+        CompareOp::Ne => left != right,
+        CompareOp::Lt => less_than(left, right),
+        CompareOp::Le => less_than(left, right) || left == right,
+        CompareOp::Gt => less_than(right, left),
+        CompareOp::Ge => less_than(right, left) || left == right,
+    }
+}
+
+// only numbers and strings are ordered, and only against their own kind
+fn less_than(left: Option<&JsonValue>, right: Option<&JsonValue>) -> bool {
+    match (left, right) {
+        (Some(JsonValue::Number(a)), Some(JsonValue::Number(b))) => compare_numbers(a, b) == Some(Ordering::Less),
+        (Some(JsonValue::String(a)), Some(JsonValue::String(b))) => a < b,
+        _ => false,
+    }
+}
+
+fn compare_numbers(a: &JsonNumber, b: &JsonNumber) -> Option<Ordering> {
+    if a == b {
+        return Some(Ordering::Equal);
+    }
+    a.as_f64().ok()?.partial_cmp(&b.as_f64().ok()?)
+}
+
+struct PathParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+// largest magnitude an index or slice bound may have (I-JSON's exact integers)
+const MAX_INDEX: i64 = (1 << 53) - 1;
+
+impl<'a> PathParser<'a> {
+    fn error(&self, msg: &str) -> String {
+        format!("Invalid JSONPath at position {}: {}", self.pos, msg)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) { Ok(()) } else { Err(self.error(&format!("expected '{}'", c))) }
+    }
+
+    fn skip_blank(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_segments(&mut self) -> Result<Vec<Segment>, String> {
+        let mut segments = vec![];
+        loop {
+            let before = self.pos;
+            self.skip_blank();
+            if self.eat_str("..") {
+                let selectors = match self.peek() {
+                    Some('[') => self.parse_bracketed()?,
+                    Some('*') => { self.pos += 1; vec![Selector::Wildcard] },
+                    _ => vec![Selector::Name(self.parse_member_name()?)],
+                };
+                segments.push(Segment::Descendant(selectors));
+            } else if self.eat('.') {
+                let selector = if self.eat('*') { Selector::Wildcard } else { Selector::Name(self.parse_member_name()?) };
+                segments.push(Segment::Child(vec![selector]));
+            } else if self.peek() == Some('[') {
+                segments.push(Segment::Child(self.parse_bracketed()?));
+            } else {
+                self.pos = before;
+                return Ok(segments);
+            }
+        }
+    }
+
+    // member-name-shorthand: a letter, '_' or non-ASCII, then those or digits
+    fn parse_member_name(&mut self) -> Result<String, String> {
+        let is_first = |c: char| c.is_ascii_alphabetic() || c == '_' || !c.is_ascii();
+        if !self.peek().is_some_and(is_first) {
+            return Err(self.error("expected a member name"));
+        }
+        let start = self.pos;
+        while let Some(c) = self.peek().filter(|&c| is_first(c) || c.is_ascii_digit()) {
+            self.pos += c.len_utf8();
+        }
+        Ok(String::from(&self.src[start..self.pos]))
+    }
+
+    fn parse_bracketed(&mut self) -> Result<Vec<Selector>, String> {
+        self.expect('[')?;
+        let mut selectors = vec![];
+        loop {
+            self.skip_blank();
+            selectors.push(self.parse_selector()?);
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(selectors);
+            }
+            if !self.eat(',') {
+                return Err(self.error("expected ',' or ']'"));
+            }
+        }
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, String> {
+        match self.peek() {
+            Some('\'' | '"') => Ok(Selector::Name(self.parse_string()?)),
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            },
+            Some('?') => {
+                self.pos += 1;
+                self.skip_blank();
+                Ok(Selector::Filter(self.parse_or()?))
+            },
+            _ => {
+                let start = self.parse_optional_int()?;
+                self.skip_blank();
+                if !self.eat(':') {
+                    return start.map(Selector::Index).ok_or_else(|| self.error("expected a selector"));
+                }
+                self.skip_blank();
+                let end = self.parse_optional_int()?;
+                self.skip_blank();
+                let step = if self.eat(':') {
+                    self.skip_blank();
+                    self.parse_optional_int()?
+                } else {
+                    None
+                };
+                Ok(Selector::Slice { start, end, step })
+            },
+        }
+    }
+
+    fn parse_optional_int(&mut self) -> Result<Option<i64>, String> {
+        let start = self.pos;
+        self.eat('-');
+        let digits_start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits = &self.src[digits_start..self.pos];
+        if digits.is_empty() {
+            if self.pos > start {
+                return Err(self.error("expected digits after '-'"));
+            }
+            return Ok(None);
+        }
+        let text = &self.src[start..self.pos];
+        if (digits.len() > 1 && digits.starts_with('0')) || text == "-0" {
+            return Err(format!("Invalid JSONPath at position {}: '{}' is not a valid integer", start, text));
+        }
+        match text.parse::<i64>() {
+            Ok(n) if (-MAX_INDEX..=MAX_INDEX).contains(&n) => Ok(Some(n)),
+            _ => Err(format!("Invalid JSONPath at position {}: {} is out of range", start, text)),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        let quote = self.peek().ok_or_else(|| self.error("expected a string"))?;
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += c.len_utf8();
+            match c {
+                c if c == quote => return Ok(out),
+                '\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match escaped {
+                        'b' => out.push('\u{8}'),
+                        'f' => out.push('\u{c}'),
+                        'n' => out.push('\n'),
+                        'r' => out.push('\r'),
+                        't' => out.push('\t'),
+                        '/' | '\\' => out.push(escaped),
+                        'u' => out.push(self.parse_unicode_escape()?),
+                        c if c == quote => out.push(c),
+                        _ => return Err(self.error(&format!("invalid escape '\\{}'", escaped))),
+                    }
+                },
+                c if c < ' ' => return Err(self.error("control characters must be escaped")),
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let hex = self.src.get(self.pos..self.pos + 4).filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()));
+        let code = hex.and_then(|h| u32::from_str_radix(h, 16).ok()).ok_or_else(|| self.error("expected four hex digits"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.parse_hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.eat_str("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            let low = self.parse_hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate"))
+    }
+
+    fn parse_or(&mut self) -> Result<LogicalExpr, String> {
+        let mut exprs = vec![self.parse_and()?];
+        loop {
+            let before = self.pos;
+            self.skip_blank();
+            if !self.eat_str("||") {
+                self.pos = before;
+                break;
+            }
+            self.skip_blank();
+            exprs.push(self.parse_and()?);
+        }
+        Ok(if exprs.len() == 1 { exprs.pop().unwrap() } else { LogicalExpr::Or(exprs) })
+    }
+
+    fn parse_and(&mut self) -> Result<LogicalExpr, String> {
+        let mut exprs = vec![self.parse_basic()?];
+        loop {
+            let before = self.pos;
+            self.skip_blank();
+            if !self.eat_str("&&") {
+                self.pos = before;
+                break;
+            }
+            self.skip_blank();
+            exprs.push(self.parse_basic()?);
+        }
+        Ok(if exprs.len() == 1 { exprs.pop().unwrap() } else { LogicalExpr::And(exprs) })
+    }
+
+    fn parse_basic(&mut self) -> Result<LogicalExpr, String> {
+        if self.eat('!') {
+            self.skip_blank();
+            if self.peek() == Some('(') {
+                return Ok(LogicalExpr::Not(Box::new(self.parse_paren()?)));
+            }
+            let start = self.pos;
+            return match self.parse_comparable()? {
+                Comparable::Query(query) => Ok(LogicalExpr::Not(Box::new(LogicalExpr::Exists(query)))),
+                Comparable::Function(function) if function.function.is_logical() => {
+                    Ok(LogicalExpr::Not(Box::new(LogicalExpr::Function(function))))
+                },
+                _ => Err(format!("Invalid JSONPath at position {}: '!' must be followed by a test or '('", start)),
+            };
+        }
+        if self.peek() == Some('(') {
+            return self.parse_paren();
+        }
+        let start = self.pos;
+        let left = self.parse_comparable()?;
+        let before_op = self.pos;
+        self.skip_blank();
+        let op = match self.parse_compare_op() {
+            Some(op) => op,
+            None => {
+                self.pos = before_op;
+                return match left {
+                    Comparable::Query(query) => Ok(LogicalExpr::Exists(query)),
+                    Comparable::Function(function) if function.function.is_logical() => Ok(LogicalExpr::Function(function)),
+                    Comparable::Function(_) => Err(format!("Invalid JSONPath at position {}: this function's result must be compared", start)),
+                    Comparable::Literal(_) => Err(format!("Invalid JSONPath at position {}: a literal must be compared", start)),
+                };
+            },
+        };
+        self.check_comparable(&left, start)?;
+        self.skip_blank();
+        let right_start = self.pos;
+        let right = self.parse_comparable()?;
+        self.check_comparable(&right, right_start)?;
+        Ok(LogicalExpr::Compare(left, op, right))
+    }
+
+    fn parse_paren(&mut self) -> Result<LogicalExpr, String> {
+        self.expect('(')?;
+        self.skip_blank();
+        let expr = self.parse_or()?;
+        self.skip_blank();
+        self.expect(')')?;
+        Ok(expr)
+    }
+
+    fn parse_compare_op(&mut self) -> Option<CompareOp> {
+        let ops = [("==", CompareOp::Eq), ("!=", CompareOp::Ne), ("<=", CompareOp::Le), (">=", CompareOp::Ge), ("<", CompareOp::Lt), (">", CompareOp::Gt)];
+        ops.into_iter().find(|(text, _)| self.eat_str(text)).map(|(_, op)| op)
+    }
+
+    // comparisons take singular queries and value-typed functions only
+    fn check_comparable(&self, comparable: &Comparable, start: usize) -> Result<(), String> {
+        match comparable {
+            Comparable::Query(query) if !is_singular(query) => {
+                Err(format!("Invalid JSONPath at position {}: only singular queries can be compared", start))
+            },
+            Comparable::Function(function) if function.function.is_logical() => {
+                Err(format!("Invalid JSONPath at position {}: a logical function cannot be compared", start))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_comparable(&mut self) -> Result<Comparable, String> {
+        match self.peek() {
+            Some('@' | '$') => {
+                let relative = self.peek() == Some('@');
+                self.pos += 1;
+                Ok(Comparable::Query(Query { relative, segments: self.parse_segments()? }))
+            },
+            Some('\'' | '"') => Ok(Comparable::Literal(JsonValue::String(self.parse_string()?))),
+            Some(c) if c.is_ascii_lowercase() => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+                    self.pos += 1;
+                }
+                let name = &self.src[start..self.pos];
+                if self.peek() == Some('(') {
+                    return self.parse_function(name, start).map(Comparable::Function);
+                }
+                match name {
+                    "true" => Ok(Comparable::Literal(JsonValue::Bool(true))),
+                    "false" => Ok(Comparable::Literal(JsonValue::Bool(false))),
+                    "null" => Ok(Comparable::Literal(JsonValue::Null)),
+                    _ => Err(format!("Invalid JSONPath at position {}: unexpected '{}'", start, name)),
+                }
+            },
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| matches!(c, '-' | '+' | '.' | 'e' | 'E') || c.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+                JsonNumber::parse(&self.src[start..self.pos])
+                    .map(|n| Comparable::Literal(JsonValue::Number(n)))
+                    .map_err(|_| format!("Invalid JSONPath at position {}: invalid number '{}'", start, &self.src[start..self.pos]))
+            },
+            _ => Err(self.error("expected a query, literal or function")),
+        }
+    }
+
+    fn parse_function(&mut self, name: &str, start: usize) -> Result<FunctionExpr, String> {
+        let function = Function::from_name(name)
+            .ok_or_else(|| format!("Invalid JSONPath at position {}: unknown function '{}'", start, name))?;
+        self.expect('(')?;
+        let mut args = vec![];
+        self.skip_blank();
+        if !self.eat(')') {
+            loop {
+                self.skip_blank();
+                let arg_start = self.pos;
+                let arg = self.parse_comparable()?;
+                if let Some(param) = function.params().get(args.len()) {
+                    check_argument(&arg, *param).map_err(|msg| format!("Invalid JSONPath at position {}: {}", arg_start, msg))?;
+                }
+                args.push(arg);
+                self.skip_blank();
+                if self.eat(')') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        if args.len() != function.params().len() {
+            return Err(format!("Invalid JSONPath at position {}: {}() takes {} argument(s)", start, name, function.params().len()));
+        }
+        let regex = match args.get(1) {
+            Some(Comparable::Literal(JsonValue::String(pattern))) if function.is_logical() => Regex::new(pattern).ok(),
+            _ => None,
+        };
+        Ok(FunctionExpr { function, args, regex })
+    }
+}
+
+fn check_argument(arg: &Comparable, param: ParamType) -> Result<(), &'static str> {
+    match (param, arg) {
+        (ParamType::Value, Comparable::Query(query)) if !is_singular(query) => Err("expected a singular query"),
+        (ParamType::Value, Comparable::Function(function)) if function.function.is_logical() => Err("expected a value, not a logical function"),
+        (ParamType::Nodes, Comparable::Query(_)) => Ok(()),
+        (ParamType::Nodes, _) => Err("expected a query"),
+        _ => Ok(()),
+    }
+}
+
+// a query that can select at most one node: names and indices only
+fn is_singular(query: &Query) -> bool {
+    query.segments.iter().all(|segment| match segment {
+        Segment::Child(selectors) => selectors.len() == 1 && matches!(selectors[0], Selector::Name(_) | Selector::Index(_)),
+        Segment::Descendant(_) => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::{query_json_path, JsonPath};
+    use crate::parse_json;
+    use crate::value::JsonValue;
+
+    const STORE: &str = r#"{ "store": {
+        "book": [
+          { "category": "reference", "author": "Nigel Rees", "title": "Sayings of the Century", "price": 8.95 },
+          { "category": "fiction", "author": "Evelyn Waugh", "title": "Sword of Honour", "price": 12.99 },
+          { "category": "fiction", "author": "Herman Melville", "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+          { "category": "fiction", "author": "J. R. R. Tolkien", "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99 }
+        ],
+        "bicycle": { "color": "red", "price": 399 }
+      } }"#;
+
+    fn values(root: &JsonValue, path: &str) -> Vec<String> {
+        query_json_path(root, path).unwrap().into_iter().map(|m| m.value.to_json_string()).collect()
+    }
+
+    fn paths(root: &JsonValue, path: &str) -> Vec<String> {
+        query_json_path(root, path).unwrap().into_iter().map(|m| m.path).collect()
+    }
+
+    #[test]
+    fn test_rfc9535_store_examples() {
+        // Table 2 of RFC 9535
+        let root = parse_json(STORE).unwrap();
+        assert_eq!(values(&root, "$.store.book[*].author"),
+            [r#""Nigel Rees""#, r#""Evelyn Waugh""#, r#""Herman Melville""#, r#""J. R. R. Tolkien""#]);
+        assert_eq!(values(&root, "$..author").len(), 4);
+        assert_eq!(values(&root, "$.store.*").len(), 2);
+        assert_eq!(values(&root, "$.store..price"), ["8.95", "12.99", "8.99", "22.99", "399"]);
+        assert_eq!(paths(&root, "$..book[2]"), ["$['store']['book'][2]"]);
+        assert_eq!(paths(&root, "$..book[-1]"), ["$['store']['book'][3]"]);
+        assert_eq!(paths(&root, "$..book[0,1]"), ["$['store']['book'][0]", "$['store']['book'][1]"]);
+        assert_eq!(paths(&root, "$..book[:2]"), ["$['store']['book'][0]", "$['store']['book'][1]"]);
+        assert_eq!(values(&root, "$..book[?@.isbn].title"), [r#""Moby Dick""#, r#""The Lord of the Rings""#]);
+        assert_eq!(values(&root, "$..book[?@.price<10].title"), [r#""Sayings of the Century""#, r#""Moby Dick""#]);
+        assert_eq!(values(&root, "$..*").len(), 27);
+    }
+
+    #[test]
+    fn test_slices() {
+        let root = parse_json(r#"["a","b","c","d","e","f","g"]"#).unwrap();
+        assert_eq!(values(&root, "$[1:3]"), [r#""b""#, r#""c""#]);
+        assert_eq!(values(&root, "$[5:]"), [r#""f""#, r#""g""#]);
+        assert_eq!(values(&root, "$[1:5:2]"), [r#""b""#, r#""d""#]);
+        assert_eq!(values(&root, "$[5:1:-2]"), [r#""f""#, r#""d""#]);
+        assert_eq!(values(&root, "$[::-1]").concat(), r#""g""f""e""d""c""b""a""#);
+        assert!(values(&root, "$[::0]").is_empty());
+        assert_eq!(values(&root, "$[-2:]"), [r#""f""#, r#""g""#]);
+    }
+
+    #[test]
+    fn test_filters_and_functions() {
+        let root = parse_json(r#"{"a":[3,5,1,2,4,6,{"b":"j"},{"b":"k"},{"b":{}},{"b":"kilo"}],"o":{"p":1,"q":2,"r":3}}"#).unwrap();
+        assert_eq!(values(&root, "$.a[?@.b == 'kilo']"), [r#"{"b":"kilo"}"#]);
+        assert_eq!(values(&root, "$.a[?@>3.5]"), ["5", "4", "6"]);
+        assert_eq!(values(&root, "$.a[?@.b]").len(), 4);
+        assert_eq!(values(&root, "$.a[?!@.b]").len(), 6);
+        assert_eq!(values(&root, "$.a[?@<2 || @.b == \"k\"]"), ["1", r#"{"b":"k"}"#]);
+        assert_eq!(values(&root, "$.a[?@>1 && !(@ == 5)]"), ["3", "2", "4", "6"]);
+        assert_eq!(values(&root, "$.a[?match(@.b, 'k.*')]"), [r#"{"b":"k"}"#, r#"{"b":"kilo"}"#]);
+        assert_eq!(values(&root, "$.a[?search(@.b, 'il')]"), [r#"{"b":"kilo"}"#]);
+        assert_eq!(values(&root, "$.a[?length(@.b) == 4]"), [r#"{"b":"kilo"}"#]);
+        assert_eq!(values(&root, "$[?count(@.*) == 3]"), [r#"{"p":1,"q":2,"r":3}"#]);
+        assert_eq!(values(&root, "$.o[?@ > $.o.p]"), ["2", "3"]);
+        assert_eq!(values(&root, "$[?value(@..b) == 'j']").len(), 0);
+        assert_eq!(values(&root, "$.a[?@.x == @.y]").len(), 10);
+    }
+
+    #[test]
+    fn test_normalized_paths() {
+        let root = parse_json(r#"{"it's":{"a\\b\u0001":[0]}}"#).unwrap();
+        assert_eq!(paths(&root, "$..[0]"), [r#"$['it\'s']['a\\b\u0001'][0]"#]);
+        assert_eq!(paths(&root, "$"), ["$"]);
+    }
+
+    #[test]
+    fn test_invalid_queries() {
+        for path in ["", "$.", "store", "$[01]", "$[-0]", "$[?@.a]]", "$[?@.* == 1]", "$[?length(@.*) == 1]",
+            "$[?count(1) == 1]", "$[?match(@.a)]", "$[?foo(@)]", "$[?1]", "$[?length(@) ]", "$[9007199254740992]",
+            "$['\\q']", "$ ", "$[?@ == 'a' ]x"] {
+            assert!(JsonPath::parse(path).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_noaa_filter() {
+        let root = parse_json(&fs::read_to_string("testfiles/noaa_climate_test.json").unwrap()).unwrap();
+        // the anomalies are strings, so a numeric comparison selects nothing
+        assert!(values(&root, "$.data[?@.anomaly < 0]").is_empty());
+        let dry = query_json_path(&root, "$.data[?match(@.anomaly, '-.*')]").unwrap();
+        assert_eq!(dry.len(), fs::read_to_string("testfiles/noaa_climate_test.json").unwrap().matches("\"anomaly\":\"-").count());
+        assert_eq!(dry[0].path, "$['data']['189512']");
+    }
+}
@@ -1,6 +1,50 @@
+pub mod canonical;
+pub mod cst;
+pub mod diagnostic;
+pub mod diff;
+pub mod error;
+pub mod formatting;
+pub mod hints;
+pub mod iregexp;
+pub mod jsonpath;
+pub mod lsp;
+pub mod merge;
+pub mod ndjson;
+pub mod number;
+pub mod object;
+pub mod outline;
+pub mod parser;
+pub mod patch;
+pub mod pointer;
+pub mod position;
+pub mod recover;
+pub mod repair;
+pub mod sequence;
+pub mod sort;
+pub mod stream;
+pub mod value;
+
+pub use canonical::canonicalize_json;
+pub use cst::Cst;
+pub use error::JsonError;
+pub use formatting::{format_range, TextEdit};
+pub use jsonpath::{query_json_path, JsonPath, PathMatch};
+pub use merge::{deep_merge, merge_patch, MergeOptions};
+pub use number::JsonNumber;
+pub use object::{DuplicateKeyPolicy, JsonObject};
+pub use outline::{folding_ranges, outline, FoldingRange, OutlineEntry};
+pub use parser::{find_duplicate_keys, parse_json, parse_json_with, DuplicateKey, JSONParser, ParseOptions};
+pub use position::{LineCol, LineIndex, Location, PositionIndex, Span};
+pub use recover::{parse_json_recovering, Recovered};
+pub use repair::{repair_json, Repaired};
+pub use sort::KeyOrder;
+pub use stream::{extract_pointer, iter_array_at, JsonStream};
+pub use value::JsonValue;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
-    WhiteSpace(char),
+    /// A run of whitespace, only produced with `NO_IGNORE_WS`.
+    WhiteSpace(String),
     OpenBrace(char),
     CloseBrace(char),
     OpenParen(char),
@@ -12,13 +56,14 @@ pub enum Token {
     StringLiteral(String),
     NumericLiteral(String),
     BoolLiteral(String),
+    NullLiteral(String),
     Eof,
 }
 
 impl Token {
     pub fn extract_value(self) -> String {
         match self {
-            Token::WhiteSpace(val)  => String::from(val),
+            Token::WhiteSpace(val)  => val,
             Token::OpenBrace(val) => String::from(val),
             Token::CloseBrace(val) => String::from(val),
             Token::OpenParen(val) => String::from(val),
@@ -30,18 +75,50 @@ impl Token {
             Token::StringLiteral(val) => val,
             Token::NumericLiteral(val) => val,
             Token::BoolLiteral(val) => val,
+            Token::NullLiteral(val) => val,
             Token::Eof => String::from(""),
         }
     }
 }
 
-const IGNORE_WS: bool = true;
-const NO_IGNORE_WS: bool = false;
+/// The token for one of the literal names `true`, `false` and `null`.
+pub(crate) fn literal_name(word: &str) -> Option<Token> {
+    match word {
+        "true" | "false" => Some(Token::BoolLiteral(String::from(word))),
+        "null" => Some(Token::NullLiteral(String::from(word))),
+        _ => None,
+    }
+}
+
+/// Finds the end of a string literal a byte at a time, starting after the
+/// opening quote. Whatever follows a backslash is skipped so `\"` does not end
+/// the literal; the escape itself is validated when the string is decoded.
+#[derive(Debug, Default)]
+pub(crate) struct StringScanner {
+    escaped: bool,
+}
+
+impl StringScanner {
+    /// Whether `byte` is the closing quote.
+    pub(crate) fn is_closing(&mut self, byte: u8) -> bool {
+        if self.escaped {
+            self.escaped = false;
+            return false;
+        }
+        self.escaped = byte == b'\\';
+        byte == b'"'
+    }
+}
+
+pub const IGNORE_WS: bool = true;
+pub const NO_IGNORE_WS: bool = false;
 
 #[derive(Debug, Default)]
 pub struct JSONLexer {
     pub input: String, // what if the json file is massive, like over a few MB?
     pub lexed_input: Vec<Token>,
+    pub spans: Vec<Span>, // byte range of each token in lexed_input
+    pub last_span: Span,
     pub pos: usize,
     pub read_pos: usize,
     pub ch: char,
@@ -52,138 +129,180 @@ impl JSONLexer {
     pub fn from(s: String, ignore_ws: bool) -> Self {
         let mut lex = Self { input: s,
             lexed_input: vec![],
+            spans: vec![],
+            last_span: Default::default(),
             pos: Default::default(),
             read_pos: Default::default(),
             ch: Default::default(),
-            ignore_ws: ignore_ws
+            ignore_ws
         };
         lex.read_char();
-        return lex;
+        lex
+    }
+
+    // positions are byte offsets into the input, so decode the char sitting at
+    // read_pos directly instead of walking the whole string with chars().nth()
+    fn char_at(&self, byte_pos: usize) -> char {
+        self.input.get(byte_pos..)
+            .and_then(|rest| rest.chars().next())
+            .unwrap_or('\0')
     }
 
     pub fn read_char(&mut self) {
         if self.read_pos >= self.input.len() { self.ch = '\0'; }
-        else { self.ch = self.input.chars().nth(self.read_pos).unwrap_or('\0'); }
+        else { self.ch = self.char_at(self.read_pos); }
         self.pos = self.read_pos;
-        self.read_pos += 1;
+        self.read_pos += self.ch.len_utf8();
     }
 
     pub fn read_n_chars(&mut self, n: usize) {
-        if self.read_pos + n >= self.input.len() {
+        if self.read_pos + n > self.input.len() {
             eprintln!("The Requested number of characters to read is beyond the end of the input buffer!");
             self.ch = '\0';
         }
         else {
-            self.ch = self.input.chars().nth(self.read_pos+n).unwrap_or('\0');
-            self.pos = self.read_pos;
-            self.read_pos += n;
+            for _ in 0..n { self.read_char(); }
         }
     }
 
     pub fn peek_char(&mut self) -> char {
         if self.read_pos >= self.input.len() { '\0' }
-        else { self.input.chars().nth(self.read_pos).unwrap_or('\0') }
+        else { self.char_at(self.read_pos) }
     }
 
     pub fn peek_n_chars(&mut self, n: usize) -> Result<&str, String> {
-        if self.read_pos + n >= self.input.len() {
-            Err(String::from("The Requested number of characters to peek is beyond the end of the input buffer!"))
+        match self.input.get(self.read_pos..(self.read_pos+n)) {
+            Some(view) => Ok(view),
+            None => Err(String::from("The Requested number of characters to peek is beyond the end of the input buffer!")),
         }
-        else { Ok(&self.input[self.read_pos..(self.read_pos+n)]) }
     }
 
-    pub fn next_numeric_literal(&mut self) -> Result<Token, String> {
-        let mut literal = String::from(self.ch);
-        loop {
-            let next = self.peek_char();
-            if !next.is_digit(10) { break; }
-            literal.push(next);
-            self.read_char();
+    // the literal is copied verbatim so formatting never changes how a number is written
+    pub fn next_numeric_literal(&mut self) -> Result<Token, JsonError> {
+        let start = self.pos;
+        let len = number::scan_number(&self.input.as_bytes()[start..])
+            .map_err(|reason| JsonError::new("Invalid number found", self.word_span()).with_help(reason))?;
+        while self.read_pos < start + len { self.read_char(); }
+        Ok(Token::NumericLiteral(String::from(&self.input[start..self.read_pos])))
+    }
+
+    pub fn next_string_literal(&mut self) -> Result<Token, JsonError> {
+        let start = self.pos;
+        let mut scanner = StringScanner::default();
+        let close = self.input.as_bytes()[start + 1..].iter().position(|&byte| scanner.is_closing(byte))
+            .ok_or_else(|| JsonError::new("Unterminated string literal", Span::new(start, self.input.len())))?;
+        while self.pos < start + 1 + close { self.read_char(); }
+        Ok(Token::StringLiteral(String::from(&self.input[start..self.read_pos])))
+    }
+
+    fn next_keyword(&mut self, keyword: &'static str) -> Result<&'static str, JsonError> {
+        let rest = keyword.len() - 1;
+        if self.input.get(self.pos..self.pos + keyword.len()) == Some(keyword) {
+            self.read_n_chars(rest);
+            Ok(keyword)
+        } else {
+            let end = (self.read_pos + rest).min(self.input.len());
+            let test_view = self.input.get(self.read_pos..end).unwrap_or("");
+            let error = JsonError::new(format!("Invalid token '{}' found", test_view), Span::new(self.read_pos, end));
+            Err(self.with_word_hint(error))
         }
-        Ok(Token::NumericLiteral(literal))
     }
 
-    pub fn next_bool_literal(&mut self) -> Result<Token, String> {
+    fn with_word_hint(&self, error: JsonError) -> JsonError {
+        match hints::word_hint(self.word_under_cursor()) {
+            Some(hint) => error.with_help(hint),
+            None => error,
+        }
+    }
+
+    // the run of letters, digits and signs starting at the cursor, for error messages
+    fn word_under_cursor(&self) -> &str {
+        let rest = self.input.get(self.pos..).unwrap_or("");
+        let end = rest.find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '+' | '.'))).unwrap_or(rest.len());
+        &rest[..end]
+    }
+
+    // the word under the cursor, or at least the character there
+    fn word_span(&self) -> Span {
+        let len = self.word_under_cursor().len().max(self.ch.len_utf8());
+        Span::new(self.pos, (self.pos + len).min(self.input.len()))
+    }
+
+    fn unexpected_token(&self) -> JsonError {
+        let char_span = Span::new(self.pos, self.pos + self.ch.len_utf8());
         match self.ch {
-            't' => {
-                let test_view: &str = self.peek_n_chars(3)?;
-                if "t".to_owned() + test_view == "true" {
-                    self.read_n_chars(3);
-                    Ok(Token::BoolLiteral(String::from("true")))
-                } else {
-                    Err(format!("Invalid token '{}' found at position {}", String::from(test_view), self.read_pos))
-                }
+            '\'' => JsonError::new("Unexpected \"'\"", char_span).with_help("JSON strings use double quotes"),
+            '\u{201c}' | '\u{201d}' | '\u{2018}' | '\u{2019}' => {
+                JsonError::new(format!("Unexpected '{}'", self.ch), char_span).with_help(hints::quote_hint(self.ch, ""))
+            },
+            '/' => JsonError::new("Unexpected '/'", char_span).with_help("JSON does not allow comments"),
+            _ => {
+                let word = self.word_under_cursor();
+                let shown = if word.is_empty() { self.ch.to_string() } else { String::from(word) };
+                self.with_word_hint(JsonError::new(format!("Unexpected '{}'", shown), self.word_span()))
             },
-            'f' => {
-                let test_view: &str = self.peek_n_chars(4)?;
-                if "f".to_owned() + test_view == "false" {
-                    self.read_n_chars(4);
-                    Ok(Token::BoolLiteral(String::from("false")))
-                }
-                else { Err(format!("Invalid token '{}' found at position {}", String::from(test_view), self.read_pos)) }
-            }
-            _ => { Err(String::from("Unrecognized token under cursor")) }
         }
     }
 
-    pub fn next_token(&mut self) -> Result<Token, String> {
-        // simple case: match current token
-        let token: Token;
+    pub fn next_bool_literal(&mut self) -> Result<Token, JsonError> {
         match self.ch {
+            't' => Ok(Token::BoolLiteral(String::from(self.next_keyword("true")?))),
+            'f' => Ok(Token::BoolLiteral(String::from(self.next_keyword("false")?))),
+            _ => Err(self.unexpected_token()),
+        }
+    }
+
+    pub fn next_null_literal(&mut self) -> Result<Token, JsonError> {
+        Ok(Token::NullLiteral(String::from(self.next_keyword("null")?)))
+    }
+
+    pub fn next_token(&mut self) -> Result<Token, JsonError> {
+        if self.ignore_ws {
+            while matches!(self.ch, '\t'|'\n'|'\r'|' ') { self.read_char(); }
+        }
+        let start = self.pos.min(self.input.len());
+        // simple case: match current token
+        let token: Token = match self.ch {
             '\t'|'\n'|'\r'|' ' => {
-                if self.ignore_ws {
-                    self.read_char();
-                    return self.next_token();
-                }
-                token = Token::WhiteSpace(self.ch);
-            }
-            '{'  => token = Token::OpenBrace('{'),
-            '}'  => token = Token::CloseBrace('}'),
-            '('  => token = Token::OpenParen('('),
-            ')'  => token = Token::CloseParen(')'),
-            '['  => token = Token::OpenBrack('['),
-            ']'  => token = Token::CloseBrack(']'),
-            ':'  => token = Token::Colon(':'),
-            ','  => token = Token::Comma(','),
-            '\0' => token = Token::Eof,
-            '"' => {
-                let mut literal = String::from("");
-                loop {
-                    self.read_char();
-                    if self.ch == '"' { break; }
-                    literal.push(self.ch);
-                }
-                token = Token::StringLiteral("\"".to_owned() + &literal + "\"");
-            },
-            '0'..='9' => {
-                token = self.next_numeric_literal().unwrap_or(Token::Eof);
+                while matches!(self.peek_char(), '\t'|'\n'|'\r'|' ') { self.read_char(); }
+                Token::WhiteSpace(String::from(&self.input[start..self.read_pos]))
             },
+            '{'  => Token::OpenBrace('{'),
+            '}'  => Token::CloseBrace('}'),
+            '('  => Token::OpenParen('('),
+            ')'  => Token::CloseParen(')'),
+            '['  => Token::OpenBrack('['),
+            ']'  => Token::CloseBrack(']'),
+            ':'  => Token::Colon(':'),
+            ','  => Token::Comma(','),
+            '\0' => Token::Eof,
+            '"' => self.next_string_literal()?,
+            '0'..='9' => self.next_numeric_literal()?,
             '-' => {
-                if !self.peek_char().is_digit(10) {
-                    return Err(format!("Invalid token '-' found at position {}", self.read_pos));
+                if !self.peek_char().is_ascii_digit() {
+                    let error = JsonError::new("Invalid token '-' found", self.word_span());
+                    return Err(self.with_word_hint(error));
                 }
-                token = self.next_numeric_literal().unwrap_or(Token::Eof);
+                self.next_numeric_literal()?
             },
-            _ => {
-                token = self.next_bool_literal().unwrap_or_else(|err| {
-                    eprintln!("{}", err);
-                    Token::Eof
-                })
-            }
-        }
+            'n' => self.next_null_literal()?,
+            't' | 'f' => self.next_bool_literal()?,
+            _ => return Err(self.unexpected_token()),
+        };
+        self.last_span = Span { start, end: self.read_pos.min(self.input.len()) };
         self.read_char();
         Ok(token)
     }
 
-    pub fn lex(&mut self) -> Result<(), String> {
+    pub fn lex(&mut self) -> Result<(), JsonError> {
         loop {
-            if let token = self.next_token()? {
-                if token == Token::Eof {
-                    self.lexed_input.push(token);
-                    break;
-                }
+            let token = self.next_token()?;
+            self.spans.push(self.last_span);
+            if token == Token::Eof {
                 self.lexed_input.push(token);
+                break;
             }
+            self.lexed_input.push(token);
         }
         Ok(())
     }
@@ -196,17 +315,126 @@ impl JSONLexer {
             .into_iter()
             .fold(String::from(""), |acc, tok| acc + &tok)
     }
+
+    /// The lexed tokens with the whitespace runs folded into their neighbours.
+    /// Whitespace up to and including the first line break after a token trails
+    /// it and the rest leads the next token, so the whitespace at the end of the
+    /// input ends up on `Eof`. Meant for the output of `lex()` in `NO_IGNORE_WS`
+    /// mode; concatenating every piece gives back the input.
+    pub fn attach_trivia(&self) -> Vec<TriviaToken> {
+        let mut tokens: Vec<TriviaToken> = vec![];
+        let mut leading = String::new();
+        for (token, span) in self.lexed_input.iter().zip(&self.spans) {
+            match token {
+                Token::WhiteSpace(run) => {
+                    let mut run = run.as_str();
+                    if let Some(previous) = tokens.last_mut() {
+                        let split = run.find('\n').map_or(run.len(), |i| i + 1);
+                        previous.trailing.push_str(&run[..split]);
+                        run = &run[split..];
+                    }
+                    leading.push_str(run);
+                },
+                token => tokens.push(TriviaToken {
+                    leading: std::mem::take(&mut leading),
+                    token: token.clone(),
+                    span: *span,
+                    trailing: String::new(),
+                }),
+            }
+        }
+        tokens
+    }
+}
+
+/// A token and the whitespace attached to it, see `JSONLexer::attach_trivia`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriviaToken {
+    pub leading: String,
+    pub token: Token,
+    /// Where the token itself is, without its trivia.
+    pub span: Span,
+    pub trailing: String,
+}
+
+impl TriviaToken {
+    pub fn to_source(&self) -> String {
+        format!("{}{}{}", self.leading, self.token.clone().extract_value(), self.trailing)
+    }
+}
+
+/// Options shared by the formatters. The default leaves the document's structure alone.
+#[derive(Debug, Clone, Default)]
+pub struct FormatConfig {
+    /// Reorder object members by key.
+    pub sort_keys: Option<KeyOrder>,
+    /// Only sort objects nested less than this many containers deep (the root is
+    /// at depth 0). `None` sorts at every level.
+    pub sort_depth: Option<usize>,
+}
+
+impl FormatConfig {
+    fn lex(&self, in_json: String) -> Result<JSONLexer, String> {
+        let mut lexer = JSONLexer::from(in_json, IGNORE_WS);
+        lexer.lex()?;
+        check_single_value(&lexer)?;
+        if let Some(order) = self.sort_keys {
+            let tokens = std::mem::take(&mut lexer.lexed_input);
+            lexer.lexed_input = sort::sort_token_keys(tokens, order, self.sort_depth)?;
+            lexer.spans.clear(); // no longer line up with the reordered tokens
+        }
+        Ok(lexer)
+    }
+}
+
+// The formatters only lex, so without this `{"a":1}{"b":2}` would be formatted
+// as if it were one value, and nesting would have no limit.
+fn check_single_value(lexer: &JSONLexer) -> Result<(), String> {
+    let mut depth = 0usize;
+    let mut seen_value = false;
+    for (token, span) in lexer.lexed_input.iter().zip(&lexer.spans) {
+        match token {
+            Token::WhiteSpace(_) | Token::Colon(_) | Token::Comma(_) | Token::Eof => continue,
+            Token::CloseBrace(_) | Token::CloseParen(_) | Token::CloseBrack(_) => {
+                depth = depth.saturating_sub(1);
+                continue;
+            },
+            _ if depth == 0 && seen_value => {
+                let shown = token.clone().extract_value();
+                return Err(format!("Unexpected token '{}' at position {} after the end of the document", shown, span.start));
+            },
+            _ => seen_value = true,
+        }
+        if matches!(token, Token::OpenBrace(_) | Token::OpenParen(_) | Token::OpenBrack(_)) {
+            if depth == parser::MAX_DEPTH {
+                return Err(format!("{} at position {}", parser::too_deep(parser::MAX_DEPTH), span.start));
+            }
+            depth += 1;
+        }
+    }
+    Ok(())
 }
 
 pub fn minify_json(in_json: String) -> Result<String, String> {
-    let mut lexer = JSONLexer::from(in_json, IGNORE_WS);
-    lexer.lex()?;
+    minify_json_with(in_json, &FormatConfig::default())
+}
+
+pub fn minify_json_with(in_json: String, config: &FormatConfig) -> Result<String, String> {
+    let lexer = config.lex(in_json)?;
     Ok(lexer.tokens_to_string())
 }
 
 pub fn prettify_json(in_json: String) -> Result<String, String> {
-    let mut lexer = JSONLexer::from(in_json, IGNORE_WS);
-    lexer.lex()?;
+    prettify_json_with(in_json, &FormatConfig::default())
+}
+
+// a newline followed by one space per level of nesting
+fn line_break(depth: u32) -> Token {
+    Token::WhiteSpace(format!("\n{}", " ".repeat(depth as usize)))
+}
+
+pub fn prettify_json_with(in_json: String, config: &FormatConfig) -> Result<String, String> {
+    let mut lexer = config.lex(in_json)?;
     // collect the items and locations to insert
     let mut to_insert: Vec<(usize, Token)> = vec![];
     let mut i = 0usize;
@@ -216,43 +444,24 @@ pub fn prettify_json(in_json: String) -> Result<String, String> {
     while let Some(token) = peekable.next() {
         match token {
             Token::OpenBrace(_) | Token::OpenParen(_) | Token::OpenBrack(_) => {
-                to_insert.push((i + accum, Token::WhiteSpace('\n')));
                 depth += 1;
+                to_insert.push((i + accum, line_break(depth)));
                 accum += 1;
-                for j in 0..depth {
-                    to_insert.push((i + accum, Token::WhiteSpace(' ')));
-                    accum += 1;
-                }
             },
             Token::CloseBrace(_) | Token::CloseParen(_) | Token::CloseBrack(_) => {
-                to_insert.push((i + accum - 1, Token::WhiteSpace('\n')));
-                accum += 1;
                 depth -= 1;
-                if let Some(token) = peekable.peek() {
-                    match token {
-                        Token::Eof => continue,
-                        _ => {
-                            for j in 0..depth {
-                                to_insert.push((i + accum - 1, Token::WhiteSpace(' ')));
-                                accum += 1;
-                            }
-                        }
-                    }
-                }
+                let indent = match peekable.peek() {
+                    Some(Token::Eof) | None => 0,
+                    Some(_) => depth,
+                };
+                to_insert.push((i + accum - 1, line_break(indent)));
+                accum += 1;
             },
-            Token::Comma(char) => {
-                if let Some(token) = peekable.peek() {
-                    match token {
-                        Token::StringLiteral(_) | Token::NumericLiteral(_) | Token::BoolLiteral(_) | Token::OpenBrace(_) => {
-                            to_insert.push((i + accum, Token::WhiteSpace('\n')));
-                            accum += 1;
-                            for j in 0..depth {
-                                to_insert.push((i + accum, Token::WhiteSpace(' ')));
-                                accum += 1;
-                            }
-                        },
-                        _ => ()
-                    }
+            Token::Comma(_) => {
+                if let Some(Token::StringLiteral(_) | Token::NumericLiteral(_) | Token::BoolLiteral(_)
+                    | Token::NullLiteral(_) | Token::OpenBrace(_)) = peekable.peek() {
+                    to_insert.push((i + accum, line_break(depth)));
+                    accum += 1;
                 }
             },
             _ => ()
@@ -271,7 +480,7 @@ mod tests {
     use std::io::Read;
     use super::{Token, JSONLexer};
     use super::{IGNORE_WS, NO_IGNORE_WS};
-    use super::{prettify_json};
+    use super::{prettify_json, prettify_json_with, minify_json, minify_json_with, parse_json, FormatConfig, KeyOrder};
 
     #[test]
     fn test_next_token() {
@@ -307,7 +516,7 @@ mod tests {
     #[test]
     fn test_next_token_neg_sign_invalid() {
         let input = String::from(r#"{"field":-a}"#);
-        let expected = vec![
+        let expected = [
             Token::OpenBrace('{'),
             Token::StringLiteral(String::from("\"field\"")),
             Token::Colon(':')
@@ -316,7 +525,7 @@ mod tests {
         for expected_token in expected.iter() {
             match lex.next_token() {
                 Err(err_str) => {
-                    assert_eq!(err_str, String::from("Invalid token '-' found at position 9"));
+                    assert_eq!(err_str.to_string(), "Invalid token '-' found at position 9");
                 },
                 Ok(token) => {
                     assert_eq!(token, *expected_token);
@@ -328,7 +537,7 @@ mod tests {
     #[test]
     fn test_next_token_neg_sign_valid() {
         let input = String::from(r#"{"field":-314159}"#);
-        let expected = vec![
+        let expected = [
             Token::OpenBrace('{'),
             Token::StringLiteral(String::from("\"field\"")),
             Token::Colon(':'),
@@ -434,52 +643,36 @@ r#"
 }
 "#);
         let expected = vec![
-            Token::WhiteSpace('\n'),
+            Token::WhiteSpace(String::from("\n")),
             Token::OpenBrace('{'),
-            Token::WhiteSpace('\n'),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from("\n    ")),
             Token::StringLiteral(String::from("\"field_1\"")),
             Token::Colon(':'),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from(" ")),
             Token::StringLiteral(String::from("\"value_1\"")),
             Token::Comma(','),
-            Token::WhiteSpace('\n'),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from("\n    ")),
             Token::StringLiteral(String::from("\"field_2\"")),
             Token::Colon(':'),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from(" ")),
             Token::NumericLiteral(String::from("-69")),
             Token::Comma(','),
-            Token::WhiteSpace('\n'),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from("\n    ")),
             Token::StringLiteral(String::from("\"field_3\"")),
             Token::Colon(':'),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from(" ")),
             Token::OpenBrack('['),
             Token::CloseBrack(']'),
             Token::Comma(','),
-            Token::WhiteSpace('\n'),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from("\n    ")),
             Token::StringLiteral(String::from("\"field_4\"")),
             Token::Colon(':'),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from(" ")),
             Token::OpenBrace('{'),
             Token::CloseBrace('}'),
-            Token::WhiteSpace('\n'),
+            Token::WhiteSpace(String::from("\n")),
             Token::CloseBrace('}'),
-            Token::WhiteSpace('\n'),
+            Token::WhiteSpace(String::from("\n")),
         ];
         let mut lex = JSONLexer::from(input, NO_IGNORE_WS);
         for expected_token in expected.iter() {
@@ -492,9 +685,44 @@ r#"
         }
     }
 
+    #[test]
+    fn test_no_ignore_ws_round_trip() {
+        for entry in fs::read_dir("testfiles").expect("failed to list 'testfiles'") {
+            let path = entry.unwrap().path();
+            let input = fs::read_to_string(&path).unwrap();
+            let mut lex = JSONLexer::from(input.clone(), NO_IGNORE_WS);
+            lex.lex().unwrap();
+            assert_eq!(lex.tokens_to_string(), input, "{}", path.display());
+            let with_trivia: String = lex.attach_trivia().iter().map(|token| token.to_source()).collect();
+            assert_eq!(with_trivia, input, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_attach_trivia() {
+        let mut lex = JSONLexer::from(String::from("  {\"a\": 1,  \n  \"b\": 2}\n"), NO_IGNORE_WS);
+        lex.lex().unwrap();
+        let pieces: Vec<(String, String, String)> = lex.attach_trivia().into_iter()
+            .map(|token| (token.leading, token.token.extract_value(), token.trailing))
+            .collect();
+        let piece = |leading: &str, token: &str, trailing: &str| (String::from(leading), String::from(token), String::from(trailing));
+        assert_eq!(pieces, [
+            piece("  ", "{", ""),
+            piece("", "\"a\"", ""),
+            piece("", ":", " "),
+            piece("", "1", ""),
+            piece("", ",", "  \n"),
+            piece("  ", "\"b\"", ""),
+            piece("", ":", " "),
+            piece("", "2", ""),
+            piece("", "}", "\n"),
+            piece("", "", ""),
+        ]);
+    }
+
     #[test]
     fn test_next_token_file() {
-        let mut file = fs::File::open("test.json").expect("failed to open 'test.json'");
+        let mut file = fs::File::open("testfiles/test.json").expect("failed to open 'testfiles/test.json'");
         let mut input = String::new();
         file.read_to_string(&mut input).expect("Failed to read the file 'test.json'");
         let expected = vec![
@@ -552,13 +780,13 @@ r#"
             Token::OpenBrace('{'),
             Token::StringLiteral(String::from("\"field_1\"")),
             Token::Colon(':'),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from(" ")),
             Token::BoolLiteral(String::from("true")),
             Token::Comma(','),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from(" ")),
             Token::StringLiteral(String::from("\"field_2\"")),
             Token::Colon(':'),
-            Token::WhiteSpace(' '),
+            Token::WhiteSpace(String::from(" ")),
             Token::BoolLiteral(String::from("false")),
             Token::CloseBrace('}'),
         ];
@@ -573,6 +801,67 @@ r#"
         }
     }
 
+    #[test]
+    fn test_next_numeric_literal_grammar() {
+        for literal in ["0", "-0.5", "3.14159", "6.02e23", "1E-9", "-2.5e+3"] {
+            let mut lex = JSONLexer::from(String::from(literal), IGNORE_WS);
+            assert_eq!(lex.next_token(), Ok(Token::NumericLiteral(String::from(literal))));
+            assert_eq!(lex.next_token(), Ok(Token::Eof));
+        }
+        for literal in ["01", "1.", "1.e5", "2e", "2e+"] {
+            let mut lex = JSONLexer::from(String::from(literal), IGNORE_WS);
+            assert!(lex.next_token().is_err(), "{}", literal);
+        }
+    }
+
+    #[test]
+    fn test_next_token_hints() {
+        let first_error = |input: &str| {
+            let mut lex = JSONLexer::from(String::from(input), IGNORE_WS);
+            lex.lex().unwrap_err().to_string()
+        };
+        assert_eq!(first_error("[True]"), "Unexpected 'True' at position 1; JSON booleans are lowercase: did you mean `true`?");
+        assert_eq!(first_error("{\"a\": None}"), "Unexpected 'None' at position 6; JSON has no `None`: did you mean `null`?");
+        assert_eq!(first_error("['a']"), "Unexpected \"'\" at position 1; JSON strings use double quotes");
+        assert!(first_error("[1, // x\n2]").ends_with("JSON does not allow comments"));
+        assert!(first_error("[NaN]").contains("NaN or Infinity"));
+        assert!(first_error("[tru]").ends_with("did you mean `true`?"));
+        assert_eq!(first_error("[@]"), "Unexpected '@' at position 1");
+    }
+
+    #[test]
+    fn test_sort_keys_formatters() {
+        let input = String::from(r#"{"b":1,"A":{"10":"\u00e9","9":2},"a":[{"z":1,"y":2}]}"#);
+        let config = FormatConfig { sort_keys: Some(KeyOrder::Lexicographic), sort_depth: None };
+        assert_eq!(minify_json_with(input.clone(), &config).unwrap(),
+            r#"{"A":{"10":"\u00e9","9":2},"a":[{"y":2,"z":1}],"b":1}"#);
+        let config = FormatConfig { sort_keys: Some(KeyOrder::Natural), sort_depth: Some(2) };
+        assert_eq!(minify_json_with(input.clone(), &config).unwrap(),
+            r#"{"A":{"9":2,"10":"\u00e9"},"a":[{"z":1,"y":2}],"b":1}"#);
+        let config = FormatConfig { sort_keys: Some(KeyOrder::CaseInsensitive), sort_depth: Some(1) };
+        assert_eq!(prettify_json_with(input, &config).unwrap(),
+            "{\n \"A\":{\n  \"10\":\"\\u00e9\",\n  \"9\":2\n },\n \"a\":[\n  {\n   \"z\":1,\n   \"y\":2\n  }\n ],\n \"b\":1\n}");
+    }
+
+    #[test]
+    fn test_sort_keys_noaa_natural() {
+        let input = fs::read_to_string("testfiles/noaa_climate_test.json").expect("failed to read 'testfiles/noaa_climate_test.json'");
+        let config = FormatConfig { sort_keys: Some(KeyOrder::Natural), sort_depth: None };
+        let sorted = parse_json(&minify_json_with(input, &config).unwrap()).unwrap();
+        let years: Vec<&String> = sorted.get("data").and_then(|data| data.as_object()).unwrap().keys().collect();
+        assert!(years.windows(2).all(|pair| pair[0].parse::<u64>().unwrap() < pair[1].parse::<u64>().unwrap()));
+        let top: Vec<&String> = sorted.as_object().unwrap().keys().collect();
+        assert_eq!(top, vec!["data", "description"]);
+    }
+
+    #[test]
+    fn test_json_minify_trailing_value() {
+        assert_eq!(minify_json(String::from("[1, [2]] \n")), Ok(String::from("[1,[2]]")));
+        let err = minify_json(String::from(r#"{"a":1} {"b":2}"#)).unwrap_err();
+        assert_eq!(err, "Unexpected token '{' at position 8 after the end of the document");
+        assert!(prettify_json(String::from("1 2")).is_err());
+    }
+
     #[test]
     fn test_json_minify_one_var_simple() {
         let input = String::from(r#"{"field":10}"#);
@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+
+use crate::cst::{Cst, CstNode};
+use crate::formatting::format_range;
+use crate::outline::{self, outline, OutlineEntry};
+use crate::parser::parse_json;
+use crate::position::{LineIndex, Span};
+use crate::recover::parse_json_recovering;
+use crate::value::JsonValue;
+use crate::{prettify_json_with, FormatConfig};
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+// a request's result, or an error code and message
+type Reply = Result<JsonValue, (i64, String)>;
+
+// Bodies larger than this are skipped unread and answered with an error.
+const MAX_MESSAGE: usize = 64 << 20;
+
+/// Reads one `Content-Length` framed message body. `None` once the input ends.
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<String>, String> {
+    match read_header(reader)? {
+        Some(length) if length > MAX_MESSAGE => Err(too_large(length)),
+        Some(length) => read_body(reader, length).map(Some),
+        None => Ok(None),
+    }
+}
+
+// the Content-Length of the next message, or `None` at the end of the input
+fn read_header(reader: &mut impl BufRead) -> Result<Option<usize>, String> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|err| err.to_string())? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(String::from("Unexpected end of input in message header")),
+            };
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>().map_err(|_| format!("Invalid Content-Length '{}'", value.trim()))?);
+            }
+        }
+    }
+    length.map(Some).ok_or_else(|| String::from("Message header has no Content-Length"))
+}
+
+fn read_body(reader: &mut impl BufRead, length: usize) -> Result<String, String> {
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|err| err.to_string())?;
+    String::from_utf8(body).map_err(|_| String::from("Message body is not UTF-8"))
+}
+
+fn too_large(length: usize) -> String {
+    format!("Message of {} bytes is over the {} byte limit", length, MAX_MESSAGE)
+}
+
+/// Writes `message` with a `Content-Length` header.
+pub fn write_message(writer: &mut impl Write, message: &JsonValue) -> Result<(), String> {
+    let body = message.to_json_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body).map_err(|err| err.to_string())?;
+    writer.flush().map_err(|err| err.to_string())
+}
+
+/// A JSON language server: diagnostics, formatting, document symbols, folding
+/// and selection ranges for the documents the client has open.
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, String>,
+    initialized: bool,
+    shutdown: bool,
+    exit_code: Option<i32>,
+}
+
+impl Server {
+    /// The exit code to stop with, once the client has sent `exit`.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Handles one message from the client, returning the messages to send back.
+    pub fn handle(&mut self, message: &JsonValue) -> Vec<JsonValue> {
+        // responses to requests we never make are ignored
+        let Some(method) = message.get("method").and_then(JsonValue::as_str) else { return vec![] };
+        let params = message.get("params").unwrap_or(&JsonValue::Null);
+        match message.get("id") {
+            Some(id) => {
+                let reply = match self.request(method, params) {
+                    Ok(result) => object(vec![("jsonrpc", "2.0".into()), ("id", id.clone()), ("result", result)]),
+                    Err((code, text)) => error_response(id.clone(), code, &text),
+                };
+                vec![reply]
+            },
+            None => self.notify(method, params),
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &JsonValue) -> Reply {
+        if method == "initialize" {
+            self.initialized = true;
+            return Ok(initialize_result());
+        }
+        if !self.initialized {
+            return Err((SERVER_NOT_INITIALIZED, String::from("Server is not initialized")));
+        }
+        if self.shutdown {
+            return Err((INVALID_REQUEST, String::from("Server is shutting down")));
+        }
+        if method == "shutdown" {
+            self.shutdown = true;
+            return Ok(JsonValue::Null);
+        }
+        let handler: fn(&str, &JsonValue) -> Reply = match method {
+            "textDocument/formatting" => |text, _| Ok(format_document(text)),
+            "textDocument/rangeFormatting" => format_document_range,
+            "textDocument/documentSymbol" => |text, _| Ok(document_symbols(text)),
+            "textDocument/foldingRange" => |text, _| Ok(folding_ranges(text)),
+            "textDocument/selectionRange" => selection_ranges,
+            _ => return Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", method))),
+        };
+        let uri = params.pointer("/textDocument/uri").and_then(JsonValue::as_str).ok_or((INVALID_PARAMS, String::from("Missing textDocument.uri")))?;
+        let text = self.documents.get(uri).ok_or_else(|| (INVALID_PARAMS, format!("Document '{}' is not open", uri)))?;
+        handler(text, params)
+    }
+
+    fn notify(&mut self, method: &str, params: &JsonValue) -> Vec<JsonValue> {
+        let uri = params.pointer("/textDocument/uri").and_then(JsonValue::as_str).map(String::from);
+        match (method, uri) {
+            ("exit", _) => {
+                self.exit_code = Some(if self.shutdown { 0 } else { 1 });
+                vec![]
+            },
+            ("textDocument/didOpen", Some(uri)) => {
+                let Some(text) = params.pointer("/textDocument/text").and_then(JsonValue::as_str) else { return vec![] };
+                self.documents.insert(uri.clone(), String::from(text));
+                vec![self.publish_diagnostics(&uri)]
+            },
+            ("textDocument/didChange", Some(uri)) => {
+                let Some(text) = self.documents.get_mut(&uri) else { return vec![] };
+                let changes = params.get("contentChanges").and_then(JsonValue::as_array).map_or(&[][..], Vec::as_slice);
+                for change in changes {
+                    apply_change(text, change);
+                }
+                vec![self.publish_diagnostics(&uri)]
+            },
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(&uri);
+                vec![diagnostics_notification(&uri, vec![])]
+            },
+            // `initialized` and anything else needs no answer
+            _ => vec![],
+        }
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> JsonValue {
+        let text = &self.documents[uri];
+        let lines = LineIndex::new(text);
+        let mut errors = parse_json_recovering(text).errors;
+        if errors.is_empty() {
+            // a few problems, such as bad escapes, only the strict parser reports
+            if let Err(err) = parse_json(text) {
+                errors.push(err);
+            }
+        }
+        let diagnostics = errors.into_iter().map(|err| {
+            let message = match err.help {
+                Some(help) => format!("{}\n{}", err.message, help),
+                None => err.message,
+            };
+            object(vec![
+                ("range", range(&lines, err.span)),
+                ("severity", 1i64.into()),
+                ("source", "json-mini".into()),
+                ("message", message.into()),
+            ])
+        });
+        diagnostics_notification(uri, diagnostics.collect())
+    }
+}
+
+/// Serves one client: reads requests from `reader` and writes replies to
+/// `writer` until the client sends `exit`. The exit code is 0 if the client
+/// asked for `shutdown` first, as the protocol expects, and 1 otherwise.
+pub fn run(mut reader: impl BufRead, mut writer: impl Write) -> Result<i32, String> {
+    let mut server = Server::default();
+    while let Some(length) = read_header(&mut reader)? {
+        let replies = if length > MAX_MESSAGE {
+            io::copy(&mut (&mut reader).take(length as u64), &mut io::sink()).map_err(|err| err.to_string())?;
+            vec![error_response(JsonValue::Null, INVALID_REQUEST, &too_large(length))]
+        } else {
+            match parse_json(&read_body(&mut reader, length)?) {
+                Ok(message) => server.handle(&message),
+                Err(err) => vec![error_response(JsonValue::Null, PARSE_ERROR, &err.to_string())],
+            }
+        };
+        for reply in replies {
+            write_message(&mut writer, &reply)?;
+        }
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+    Ok(1)
+}
+
+fn initialize_result() -> JsonValue {
+    let capabilities = object(vec![
+        // the client sends the whole text on every change
+        ("textDocumentSync", 1i64.into()),
+        ("documentFormattingProvider", true.into()),
+        ("documentRangeFormattingProvider", true.into()),
+        ("documentSymbolProvider", true.into()),
+        ("foldingRangeProvider", true.into()),
+        ("selectionRangeProvider", true.into()),
+    ]);
+    let server_info = object(vec![("name", "json-mini-lsp".into()), ("version", env!("CARGO_PKG_VERSION").into())]);
+    object(vec![("capabilities", capabilities), ("serverInfo", server_info)])
+}
+
+// a change with a range replaces that part of the text, one without replaces all of it
+fn apply_change(text: &mut String, change: &JsonValue) {
+    let Some(new_text) = change.get("text").and_then(JsonValue::as_str) else { return };
+    match change.get("range") {
+        Some(edit_range) => {
+            let lines = LineIndex::new(text);
+            let (Some(start), Some(end)) = (offset(&lines, edit_range.get("start")), offset(&lines, edit_range.get("end"))) else { return };
+            text.replace_range(start..end.max(start), new_text);
+        },
+        None => *text = String::from(new_text),
+    }
+}
+
+fn format_document(text: &str) -> JsonValue {
+    let Ok(mut formatted) = prettify_json_with(String::from(text), &FormatConfig::default()) else { return JsonValue::Null };
+    if text.ends_with('\n') {
+        formatted.push('\n');
+    }
+    if formatted == text {
+        return JsonValue::Array(vec![]);
+    }
+    let lines = LineIndex::new(text);
+    JsonValue::Array(vec![text_edit(&lines, Span::new(0, text.len()), formatted)])
+}
+
+fn format_document_range(text: &str, params: &JsonValue) -> Reply {
+    let lines = LineIndex::new(text);
+    let (Some(start), Some(end)) = (offset(&lines, params.pointer("/range/start")), offset(&lines, params.pointer("/range/end"))) else {
+        return Err((INVALID_PARAMS, String::from("Missing or invalid range")));
+    };
+    let Ok(edits) = format_range(text, start..end.max(start), &FormatConfig::default()) else { return Ok(JsonValue::Null) };
+    Ok(JsonValue::Array(edits.into_iter().map(|edit| text_edit(&lines, edit.span, edit.new_text)).collect()))
+}
+
+fn document_symbols(text: &str) -> JsonValue {
+    let Ok(entries) = outline(text) else { return JsonValue::Null };
+    JsonValue::Array(symbols(&entries, text, &LineIndex::new(text)))
+}
+
+fn symbols(entries: &[OutlineEntry], text: &str, lines: &LineIndex) -> Vec<JsonValue> {
+    entries.iter().map(|entry| {
+        let mut members = vec![
+            ("name", entry.name().into()),
+            ("kind", symbol_kind(entry.kind).into()),
+            ("range", range(lines, entry.span)),
+            ("selectionRange", range(lines, entry.name_span)),
+        ];
+        if !matches!(entry.kind, "object" | "array") {
+            members.push(("detail", text[entry.value_span.start..entry.value_span.end].into()));
+        }
+        members.push(("children", JsonValue::Array(symbols(&entry.children, text, lines))));
+        object(members)
+    }).collect()
+}
+
+// the LSP SymbolKind for a value
+fn symbol_kind(kind: &str) -> i64 {
+    match kind {
+        "object" => 19,
+        "array" => 18,
+        "string" => 15,
+        "number" => 16,
+        "boolean" => 17,
+        _ => 21,
+    }
+}
+
+// only containers spanning more than one line fold, and they fold to the line
+// before the closing bracket so the bracket stays visible
+fn folding_ranges(text: &str) -> JsonValue {
+    let Ok(folds) = outline::folding_ranges(text) else { return JsonValue::Null };
+    let folds = folds.into_iter()
+        .filter(|fold| fold.end_line > fold.start_line + 1)
+        .map(|fold| object(vec![("startLine", (fold.start_line as u64 - 1).into()), ("endLine", (fold.end_line as u64 - 2).into())]));
+    JsonValue::Array(folds.collect())
+}
+
+fn selection_ranges(text: &str, params: &JsonValue) -> Reply {
+    let lines = LineIndex::new(text);
+    let positions = params.get("positions").and_then(JsonValue::as_array).ok_or((INVALID_PARAMS, String::from("Missing positions")))?;
+    let Ok(cst) = Cst::parse(text) else { return Ok(JsonValue::Null) };
+    let mut ranges = vec![];
+    for position in positions {
+        let offset = offset(&lines, Some(position)).ok_or((INVALID_PARAMS, String::from("Invalid position")))?;
+        let mut chain = vec![];
+        selection_chain(cst.root(), offset, &mut chain);
+        chain.dedup();
+        let nested = chain.into_iter().fold(None, |parent, span| {
+            let mut members = vec![("range", range(&lines, span))];
+            members.extend(parent.map(|parent| ("parent", parent)));
+            Some(object(members))
+        });
+        ranges.push(nested.unwrap_or(JsonValue::Null));
+    }
+    Ok(JsonValue::Array(ranges))
+}
+
+// the spans around `offset`, outermost first: each container, then the member
+// in it, then the key or the value
+fn selection_chain(node: &CstNode, offset: usize, chain: &mut Vec<Span>) {
+    chain.push(node.span());
+    let CstNode::Container { items, .. } = node else { return };
+    for item in items {
+        let value = item.value.span();
+        let Some((key, _)) = &item.key else {
+            if value.start <= offset && offset <= value.end {
+                return selection_chain(&item.value, offset, chain);
+            }
+            continue;
+        };
+        let member = key.span.to(value);
+        if offset < member.start || member.end < offset {
+            continue;
+        }
+        chain.push(member);
+        if offset <= key.span.end {
+            chain.push(key.span);
+        } else {
+            selection_chain(&item.value, offset, chain);
+        }
+        return;
+    }
+}
+
+fn offset(lines: &LineIndex, position: Option<&JsonValue>) -> Option<usize> {
+    let position = position?;
+    let line = position.get("line")?.as_number()?.as_u64().ok()?;
+    let character = position.get("character")?.as_number()?.as_u64().ok()?;
+    Some(lines.utf16_offset(line as usize, character as usize))
+}
+
+fn position(lines: &LineIndex, offset: usize) -> JsonValue {
+    let (line, character) = lines.utf16_position(offset);
+    object(vec![("line", (line as u64).into()), ("character", (character as u64).into())])
+}
+
+fn range(lines: &LineIndex, span: Span) -> JsonValue {
+    object(vec![("start", position(lines, span.start)), ("end", position(lines, span.end))])
+}
+
+fn text_edit(lines: &LineIndex, span: Span, new_text: String) -> JsonValue {
+    object(vec![("range", range(lines, span)), ("newText", new_text.into())])
+}
+
+fn diagnostics_notification(uri: &str, diagnostics: Vec<JsonValue>) -> JsonValue {
+    let params = object(vec![("uri", uri.into()), ("diagnostics", JsonValue::Array(diagnostics))]);
+    object(vec![("jsonrpc", "2.0".into()), ("method", "textDocument/publishDiagnostics".into()), ("params", params)])
+}
+
+fn error_response(id: JsonValue, code: i64, message: &str) -> JsonValue {
+    let error = object(vec![("code", code.into()), ("message", message.into())]);
+    object(vec![("jsonrpc", "2.0".into()), ("id", id), ("error", error)])
+}
+
+fn object(members: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(members.into_iter().map(|(key, value)| (String::from(key), value)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{read_message, run};
+    use crate::parser::parse_json;
+    use crate::value::JsonValue;
+
+    // plays `messages` to the server as a client would and collects what it sends back
+    fn run_script(messages: &[String]) -> (i32, Vec<JsonValue>) {
+        let input: String = messages.iter().map(|body| format!("Content-Length: {}\r\n\r\n{}", body.len(), body)).collect();
+        let mut output = vec![];
+        let code = run(Cursor::new(input), &mut output).unwrap();
+        let mut reader = Cursor::new(output);
+        let mut replies = vec![];
+        while let Some(body) = read_message(&mut reader).unwrap() {
+            replies.push(parse_json(&body).unwrap());
+        }
+        (code, replies)
+    }
+
+    fn request(id: u64, method: &str, params: &str) -> String {
+        format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"method\":\"{}\",\"params\":{}}}", id, method, params)
+    }
+
+    fn notification(method: &str, params: &str) -> String {
+        format!("{{\"jsonrpc\":\"2.0\",\"method\":\"{}\",\"params\":{}}}", method, params)
+    }
+
+    fn open(text: &str) -> String {
+        let document = JsonValue::from(text).to_json_string();
+        notification("textDocument/didOpen", &format!("{{\"textDocument\":{{\"uri\":\"file:///a.json\",\"languageId\":\"json\",\"version\":1,\"text\":{}}}}}", document))
+    }
+
+    const DOC: &str = "{\"textDocument\":{\"uri\":\"file:///a.json\"}}";
+
+    fn reply(replies: &[JsonValue], id: u64) -> &JsonValue {
+        replies.iter().find(|reply| reply.get("id") == Some(&JsonValue::from(id))).unwrap()
+    }
+
+    fn get<'a>(value: &'a JsonValue, pointer: &str) -> &'a JsonValue {
+        value.pointer(pointer).unwrap_or_else(|| panic!("no {} in {}", pointer, value.to_json_string()))
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let (code, replies) = run_script(&[
+            request(1, "textDocument/formatting", DOC),
+            request(2, "initialize", "{}"),
+            notification("initialized", "{}"),
+            request(3, "textDocument/hover", DOC),
+            String::from("{not json"),
+            request(4, "shutdown", "null"),
+            notification("exit", "null"),
+        ]);
+        assert_eq!(code, 0);
+        assert_eq!(get(reply(&replies, 1), "/error/code"), &JsonValue::from(-32002i64));
+        assert_eq!(get(reply(&replies, 2), "/result/capabilities/documentSymbolProvider"), &JsonValue::from(true));
+        assert_eq!(get(reply(&replies, 3), "/error/code"), &JsonValue::from(-32601i64));
+        assert_eq!(get(&replies[3], "/error/code"), &JsonValue::from(-32700i64));
+        assert_eq!(get(reply(&replies, 4), "/result"), &JsonValue::Null);
+        // exiting without a shutdown is an error
+        let (code, _) = run_script(&[request(1, "initialize", "{}"), notification("exit", "null")]);
+        assert_eq!(code, 1);
+        // an oversized body is skipped instead of buffered
+        let mut output = vec![];
+        run(Cursor::new(format!("Content-Length: {}\r\n\r\n[]", super::MAX_MESSAGE + 1)), &mut output).unwrap();
+        let body = read_message(&mut Cursor::new(output)).unwrap().unwrap();
+        assert_eq!(get(&parse_json(&body).unwrap(), "/error/code"), &JsonValue::from(-32600i64));
+        assert!(read_message(&mut Cursor::new(format!("Content-Length: {}\r\n\r\n", usize::MAX))).is_err());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let change = notification("textDocument/didChange", "{\"textDocument\":{\"uri\":\"file:///a.json\",\"version\":2},\"contentChanges\":[{\"text\":\"[1]\"}]}");
+        let (_, replies) = run_script(&[request(1, "initialize", "{}"), open("{\n  \"a\": 1,\n  'b': 2\n}"), change]);
+        let diagnostics = get(&replies[1], "/params/diagnostics").as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(get(&diagnostics[0], "/range/start"), &parse_json("{\"line\":2,\"character\":2}").unwrap());
+        assert!(get(&diagnostics[0], "/message").as_str().unwrap().contains("double quotes"));
+        assert_eq!(get(&replies[2], "/params/diagnostics"), &JsonValue::Array(vec![]));
+    }
+
+    #[test]
+    fn test_formatting() {
+        let range = "{\"textDocument\":{\"uri\":\"file:///a.json\"},\"range\":{\"start\":{\"line\":1,\"character\":7},\"end\":{\"line\":1,\"character\":8}},\"options\":{}}";
+        let (_, replies) = run_script(&[
+            request(1, "initialize", "{}"),
+            open("{\n \"a\":[1,2],\n \"b\":  3\n}\n"),
+            request(2, "textDocument/formatting", DOC),
+            request(3, "textDocument/rangeFormatting", range),
+        ]);
+        let edits = get(reply(&replies, 2), "/result").as_array().unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(get(&edits[0], "/newText"), &JsonValue::from("{\n \"a\":[\n  1,\n  2\n ],\n \"b\":3\n}\n"));
+        assert_eq!(get(&edits[0], "/range/end"), &parse_json("{\"line\":4,\"character\":0}").unwrap());
+        let edits = get(reply(&replies, 3), "/result").as_array().unwrap();
+        assert_eq!(edits.len(), 3);
+        assert_eq!(get(&edits[0], "/newText"), &JsonValue::from("\n  "));
+    }
+
+    #[test]
+    fn test_symbols_folding_and_selection() {
+        let positions = "{\"textDocument\":{\"uri\":\"file:///a.json\"},\"positions\":[{\"line\":2,\"character\":3},{\"line\":1,\"character\":3}]}";
+        let (_, replies) = run_script(&[
+            request(1, "initialize", "{}"),
+            open("{\n \"a\":{\n  \"b\":[true,\n   null]\n },\n \"c\":\"x\"\n}"),
+            request(2, "textDocument/documentSymbol", DOC),
+            request(3, "textDocument/foldingRange", DOC),
+            request(4, "textDocument/selectionRange", positions),
+        ]);
+        let symbols = get(reply(&replies, 2), "/result");
+        assert_eq!(get(symbols, "/0/name"), &JsonValue::from("a"));
+        assert_eq!(get(symbols, "/0/kind"), &JsonValue::from(19i64));
+        assert_eq!(get(symbols, "/0/children/0/children/1/kind"), &JsonValue::from(21i64));
+        assert_eq!(get(symbols, "/0/children/0/children/1/name"), &JsonValue::from("1"));
+        assert_eq!(get(symbols, "/1/detail"), &JsonValue::from("\"x\""));
+        assert_eq!(get(symbols, "/1/selectionRange/end"), &parse_json("{\"line\":5,\"character\":4}").unwrap());
+
+        let folds = get(reply(&replies, 3), "/result");
+        assert_eq!(folds, &parse_json("[{\"startLine\":0,\"endLine\":5},{\"startLine\":1,\"endLine\":3}]").unwrap());
+
+        // inside the key "b": key, member, object, member, root
+        let chain = get(reply(&replies, 4), "/result/0");
+        assert_eq!(get(chain, "/range/end"), &parse_json("{\"line\":2,\"character\":5}").unwrap());
+        assert_eq!(get(chain, "/parent/range/end"), &parse_json("{\"line\":3,\"character\":8}").unwrap());
+        assert_eq!(get(chain, "/parent/parent/parent/parent/range/start"), &parse_json("{\"line\":0,\"character\":0}").unwrap());
+        assert!(chain.pointer("/parent/parent/parent/parent/parent").is_none());
+        // on the key "a" itself
+        let chain = get(reply(&replies, 4), "/result/1");
+        assert_eq!(get(chain, "/range/end"), &parse_json("{\"line\":1,\"character\":4}").unwrap());
+    }
+}
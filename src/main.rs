@@ -1,43 +1,664 @@
 use std::env;
 use std::process;
-use std::fs::File;
-use std::io::{Read, Error};
+use std::fs::{self, File};
+use std::io::{BufWriter, IsTerminal, Read, Error, Write};
 
-mod lib;
-use lib::{minify_json, prettify_json};
+use json_mini::{Cst, minify_json_with, prettify_json_with, FormatConfig, KeyOrder};
+use json_mini::{parse_json, DuplicateKey, DuplicateKeyPolicy, JSONLexer, JSONParser, JsonError, JsonObject, JsonValue, LineCol, LineIndex, ParseOptions, PositionIndex, Span, IGNORE_WS};
+use json_mini::canonical::canonicalize_value;
+use json_mini::diagnostic::{render_diagnostic, render_diagnostics, Level};
+use json_mini::diff::{diff_to_json, diff_values, render_diff, DiffOptions};
+use json_mini::jsonpath::query_json_path;
+use json_mini::ndjson::{explode_json, format_ndjson, slurp_ndjson, validate_ndjson};
+use json_mini::merge::{merge_all, ArrayMerge, MergeOptions, NullMerge};
+use json_mini::parser::find_duplicate_keys;
+use json_mini::patch::{apply_op, apply_patch, diff_to_patch, PatchOp};
+use json_mini::pointer::parse_pointer;
+use json_mini::recover::{lex_tolerant, parse_json_recovering, Lexeme};
+use json_mini::repair::repair_json;
+use json_mini::sequence::{parse_sequence, SequenceFormat, RECORD_SEPARATOR};
+use json_mini::stream::{extract_pointer, iter_array_at};
+use json_mini::value::escape_json_string;
 
-pub fn get_file_as_string(
-    mut args: impl Iterator<Item = String>,
-) -> Result<String, &'static str> {
-    args.next(); // first val in env::args() is name of program
-    let file_path = match args.next() {
-        Some(arg) => arg,
-        None => return Err("Didn't get a file path"),
-    };
+/// Command line split into `--flag` / `--flag=value` options and positional arguments.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub flags: Vec<(String, Option<String>)>,
+    pub positional: Vec<String>,
+}
+
+impl CliArgs {
+    pub fn parse(args: impl Iterator<Item = String>) -> Self {
+        let mut cli = CliArgs::default();
+        for arg in args {
+            match arg.strip_prefix("--") {
+                Some(flag) => match flag.split_once('=') {
+                    Some((name, value)) => cli.flags.push((String::from(name), Some(String::from(value)))),
+                    None => cli.flags.push((String::from(flag), None)),
+                },
+                None => cli.positional.push(arg),
+            }
+        }
+        cli
+    }
+
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|(flag, _)| flag == name)
+    }
 
-    let mut file = File::open(file_path).expect("Failed to open input file");
+    pub fn flag_value(&self, name: &str) -> Option<&str> {
+        self.flags.iter().rev()
+            .find(|(flag, _)| flag == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+}
+
+pub fn get_file_as_string(file_path: &str) -> Result<String, String> {
+    let mut file = File::open(file_path).map_err(|err| format!("Failed to open input file '{file_path}': {err}"))?;
     let mut file_as_str = String::new();
-    file.read_to_string(&mut file_as_str).expect("Failed to read input file");
+    file.read_to_string(&mut file_as_str).map_err(|err| format!("Failed to read input file '{file_path}': {err}"))?;
     Ok(file_as_str)
 }
 
-fn main() -> Result<(), Error> {
-	let input_json = get_file_as_string(env::args()).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {err}");
-		process::exit(1);
-	});
+fn parse_duplicate_key_policy(name: &str) -> Result<DuplicateKeyPolicy, String> {
+    match name {
+        "error" => Ok(DuplicateKeyPolicy::Error),
+        "warn" => Ok(DuplicateKeyPolicy::Warn),
+        "first" | "keep-first" => Ok(DuplicateKeyPolicy::KeepFirst),
+        "last" | "keep-last" => Ok(DuplicateKeyPolicy::KeepLast),
+        _ => Err(format!("Unknown duplicate key policy '{name}', expected one of error, warn, keep-first, keep-last")),
+    }
+}
 
-    let min_json = minify_json(input_json).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {err}");
-		process::exit(1);
-    });
+/// `--sort-keys[=lexicographic|natural|case-insensitive]` and `--sort-depth=N`.
+fn format_config(args: &CliArgs) -> Result<FormatConfig, String> {
+    let mut config = FormatConfig::default();
+    if args.has_flag("sort-keys") {
+        config.sort_keys = Some(match args.flag_value("sort-keys") {
+            Some(order) => KeyOrder::from_name(order)?,
+            None => KeyOrder::Lexicographic,
+        });
+    }
+    if let Some(depth) = args.flag_value("sort-depth") {
+        config.sort_depth = Some(depth.parse().map_err(|_| format!("Invalid --sort-depth '{depth}', expected a number"))?);
+    }
+    Ok(config)
+}
+
+/// A repeated key as a diagnostic pointing at the repeat.
+fn duplicate_key_error(index: &LineIndex, dup: &DuplicateKey) -> JsonError {
+    JsonError::new(format!("duplicate key {}", escape_json_string(&dup.key)), dup.duplicate)
+        .with_help(format!("first defined at {}", index.line_col(dup.first.start)))
+}
+
+/// `--lint-duplicates`: report every repeated key and fail if there were any.
+fn lint_duplicates(args: &CliArgs, file_path: &str, input_json: &str) -> Result<(), String> {
+    let duplicates = find_duplicate_keys(input_json).map_err(|err| diagnose(args, file_path, input_json, err))?;
+    let index = LineIndex::new(input_json);
+    let color = use_color(args, std::io::stdout().is_terminal())?;
+    for dup in duplicates.iter() {
+        println!("{}", render_diagnostic(input_json, file_path, Level::Error, &duplicate_key_error(&index, dup), color));
+    }
+    if duplicates.is_empty() { Ok(()) }
+    else { Err(format!("found {} duplicate key(s)", duplicates.len())) }
+}
+
+/// Parses with an explicit duplicate key policy and pretty prints the resolved tree.
+fn format_with_policy(args: &CliArgs, file_path: &str, input_json: String, policy: DuplicateKeyPolicy) -> Result<String, String> {
+    parse_with_policy(args, file_path, &input_json, policy)?.to_pretty_string()
+}
+
+// Parses under `policy`, warning about each repeated key with `Warn`. A repeat
+// that fails the parse with `Error` is shown the same way, at its line and column.
+fn parse_with_policy(args: &CliArgs, file_path: &str, input_json: &str, policy: DuplicateKeyPolicy) -> Result<JsonValue, String> {
+    let index = LineIndex::new(input_json);
+    let mut lexer = JSONLexer::from(String::from(input_json), IGNORE_WS);
+    lexer.lex().map_err(|err| diagnose(args, file_path, input_json, err))?;
+    let mut parser = JSONParser::from_lexer(lexer, ParseOptions { duplicate_keys: policy, ..Default::default() });
+    let value = parser.parse().map_err(|err| match parser.duplicates.last() {
+        Some(dup) if policy == DuplicateKeyPolicy::Error && dup.duplicate == err.span => {
+            report_errors(args, file_path, input_json, err.to_string(), vec![duplicate_key_error(&index, dup)])
+        },
+        _ => diagnose(args, file_path, input_json, err),
+    })?;
+    if policy == DuplicateKeyPolicy::Warn {
+        let color = use_color(args, std::io::stderr().is_terminal())?;
+        for dup in parser.duplicates.iter() {
+            eprintln!("{}", render_diagnostic(input_json, file_path, Level::Warning, &duplicate_key_error(&index, dup), color));
+        }
+    }
+    Ok(value)
+}
+
+/// The positional argument at `i`, or an error naming what was expected there.
+fn positional_arg<'a>(args: &'a CliArgs, i: usize, what: &str) -> Result<&'a str, String> {
+    args.positional.get(i).map(String::as_str).ok_or_else(|| format!("Didn't get {what}"))
+}
+
+// a stray argument is most likely a value with spaces that wasn't quoted
+fn no_more_args(args: &CliArgs, count: usize) -> Result<(), String> {
+    match args.positional.get(count) {
+        Some(extra) => Err(format!("Unexpected argument '{extra}'; quote values that contain spaces")),
+        None => Ok(()),
+    }
+}
+
+/// `json-mini canon <file>`: RFC 8785 canonical form, without a trailing newline
+/// so the output can be hashed as is.
+fn run_canon(args: &CliArgs) -> Result<i32, String> {
+    let file_path = positional_arg(args, 1, "a file path")?;
+    let input_json = get_file_as_string(file_path)?;
+    // RFC 8785 only accepts I-JSON, so repeated keys are rejected
+    let value = parse_with_policy(args, file_path, &input_json, DuplicateKeyPolicy::Error)?;
+    print!("{}", canonicalize_value(&value)?);
+    Ok(0)
+}
+
+/// `--color=always|never|auto`; auto colors only a terminal and honours NO_COLOR.
+fn use_color(args: &CliArgs, is_terminal: bool) -> Result<bool, String> {
+    match args.flag_value("color").unwrap_or("auto") {
+        "always" => Ok(true),
+        "never" => Ok(false),
+        "auto" => Ok(is_terminal && env::var_os("NO_COLOR").is_none()),
+        other => Err(format!("Invalid --color '{other}', expected always, never or auto")),
+    }
+}
 
-    let pretty_json = prettify_json(min_json).unwrap_or_else(|err| {
-        eprintln!("Problem prettifying json {err}");
+/// `json-mini diff <a> <b> [--ignore-key-order] [--json]`: exits with 1 when the
+/// documents differ, like diff(1).
+fn run_diff(args: &CliArgs) -> Result<i32, String> {
+    let a = parse_file(args, positional_arg(args, 1, "the first file path")?)?;
+    let b = parse_file(args, positional_arg(args, 2, "the second file path")?)?;
+    let options = DiffOptions { ignore_key_order: args.has_flag("ignore-key-order") };
+    let entries = diff_values(&a, &b, &options);
+    if args.has_flag("json") {
+        println!("{}", diff_to_json(&entries).to_pretty_string()?);
+    } else {
+        print!("{}", render_diff(&entries, use_color(args, std::io::stdout().is_terminal())?));
+    }
+    Ok(if entries.is_empty() { 0 } else { 1 })
+}
+
+/// Prints a result document, pretty unless `--minify` was given.
+fn print_value(args: &CliArgs, value: &JsonValue) -> Result<(), String> {
+    if args.has_flag("minify") {
+        println!("{}", value.to_json_string());
+    } else {
+        println!("{}", value.to_pretty_string()?);
+    }
+    Ok(())
+}
+
+fn parse_file(args: &CliArgs, file_path: &str) -> Result<JsonValue, String> {
+    let input = get_file_as_string(file_path)?;
+    parse_json(&input).map_err(|err| diagnose(args, file_path, &input, err))
+}
+
+/// Turns a failure to parse `input` into diagnostics on stderr, giving back the
+/// one-line message to exit with. The recovering parser finds the syntax errors;
+/// anything else is shown where the strict parser found it.
+fn diagnose(args: &CliArgs, file_path: &str, input: &str, err: JsonError) -> String {
+    let mut errors = parse_json_recovering(input).errors;
+    if errors.is_empty() {
+        errors.push(err.clone());
+    }
+    report_errors(args, file_path, input, err.to_string(), errors)
+}
+
+// Like `diagnose`, for failures that don't say where they are.
+fn diagnose_message(args: &CliArgs, file_path: &str, input: &str, err: String) -> String {
+    let errors = parse_json_recovering(input).errors;
+    report_errors(args, file_path, input, err, errors)
+}
+
+// Like `diagnose`, for files that may have comments in them: only a block
+// comment that is never closed is a mistake.
+fn diagnose_commented(args: &CliArgs, file_path: &str, input: &str, err: String) -> String {
+    let comments: Vec<Span> = lex_tolerant(input).into_iter()
+        .filter(|(lexeme, _)| *lexeme == Lexeme::Comment)
+        .map(|(_, span)| span)
+        .collect();
+    let errors: Vec<JsonError> = parse_json_recovering(input).errors.into_iter().filter_map(|error| {
+        if !comments.contains(&error.span) {
+            return Some(error);
+        }
+        let text = &input[error.span.start..error.span.end];
+        let closed = !text.starts_with("/*") || (text.len() >= 4 && text.ends_with("*/"));
+        (!closed).then(|| JsonError::new("Unterminated comment", error.span).with_help("close it with `*/`"))
+    }).collect();
+    report_errors(args, file_path, input, err, errors)
+}
+
+// Like `diagnose`, for input holding several documents, where the recovering
+// parser would take everything after the first one for a mistake.
+fn diagnose_sequence(args: &CliArgs, file_path: &str, input: &str, err: JsonError) -> String {
+    report_errors(args, file_path, input, err.to_string(), vec![err])
+}
+
+// Streaming commands don't keep the input, so on failure the file is read again
+// to show where the problem is. Stdin can't be read twice.
+fn diagnose_file(args: &CliArgs, file_path: &str, err: String) -> String {
+    match get_file_as_string(file_path) {
+        Ok(input) if file_path != "-" => diagnose_message(args, file_path, &input, err),
+        _ => format!("{file_path}: {err}"),
+    }
+}
+
+fn report_errors(args: &CliArgs, file_path: &str, input: &str, err: String, errors: Vec<JsonError>) -> String {
+    if errors.is_empty() {
+        return format!("{file_path}: {err}");
+    }
+    let color = use_color(args, std::io::stderr().is_terminal()).unwrap_or(false);
+    eprintln!("{}", render_diagnostics(input, file_path, Level::Error, &errors, color));
+    format!("{file_path}: could not parse, {} error(s)", errors.len())
+}
+
+/// `json-mini patch <doc> <patch> [--in-place]`: applies an RFC 6902 patch. The
+/// patch is all or nothing, so on failure the document is left as it was.
+fn run_patch(args: &CliArgs) -> Result<i32, String> {
+    let doc_path = positional_arg(args, 1, "a document path")?;
+    let doc = parse_file(args, doc_path)?;
+    let patch = parse_file(args, positional_arg(args, 2, "a patch path")?)?;
+    let patched = apply_patch(&doc, &patch)?;
+    if args.has_flag("in-place") {
+        let text = if args.has_flag("minify") { patched.to_json_string() } else { patched.to_pretty_string()? };
+        write_atomically(doc_path, &(text + "\n"))?;
+        return Ok(0);
+    }
+    print_value(args, &patched)?;
+    Ok(0)
+}
+
+/// `json-mini mkpatch <a> <b>`: the RFC 6902 patch that turns a into b.
+fn run_mkpatch(args: &CliArgs) -> Result<i32, String> {
+    let a = parse_file(args, positional_arg(args, 1, "the first file path")?)?;
+    let b = parse_file(args, positional_arg(args, 2, "the second file path")?)?;
+    print_value(args, &diff_to_patch(&a, &b))?;
+    Ok(0)
+}
+
+/// `json-mini get <file> <pointer>`: prints the subtree at a JSON Pointer.
+/// `--raw` prints a selected string without quotes or escapes. `--stream` keeps
+/// only the selected value in memory instead of parsing the whole file; a key
+/// on the way to it that is repeated is an error.
+fn run_get(args: &CliArgs) -> Result<i32, String> {
+    let file_path = positional_arg(args, 1, "a file path")?;
+    let pointer = positional_arg(args, 2, "a JSON Pointer")?;
+    parse_pointer(pointer)?;
+    let selected = if args.has_flag("stream") {
+        let file = File::open(file_path).map_err(|err| format!("Failed to open input file '{file_path}': {err}"))?;
+        extract_pointer(file, pointer).map_err(|err| diagnose_file(args, file_path, err))?
+    } else {
+        parse_file(args, file_path)?.pointer(pointer).cloned()
+    };
+    let selected = match selected {
+        Some(selected) => selected,
+        None => return Err(format!("{file_path}: nothing at '{pointer}'")),
+    };
+    match &selected {
+        JsonValue::String(s) if args.has_flag("raw") => println!("{s}"),
+        _ => print_value(args, &selected)?,
+    }
+    Ok(0)
+}
+
+/// `json-mini explode <file> [pointer]`: prints each element of the array at
+/// `pointer` as one minified line, reading the file an element at a time. A file
+/// of `-` reads stdin.
+fn run_explode(args: &CliArgs) -> Result<i32, String> {
+    let file_path = positional_arg(args, 1, "a file path")?;
+    let pointer = args.positional.get(2).map_or("", String::as_str);
+    let reader: Box<dyn Read> = if file_path == "-" {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(File::open(file_path).map_err(|err| format!("Failed to open input file '{file_path}': {err}"))?)
+    };
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    let mut elements = iter_array_at(reader, pointer).map_err(|err| diagnose_file(args, file_path, err))?;
+    while let Some(element) = elements.next_text() {
+        let element = element.map_err(|err| diagnose_file(args, file_path, err))?;
+        writeln!(out, "{element}").map_err(|err| format!("Failed to write output: {err}"))?;
+    }
+    out.flush().map_err(|err| format!("Failed to write output: {err}"))?;
+    Ok(0)
+}
+
+/// `json-mini ndjson <file>`: checks each line of newline-delimited JSON on its
+/// own and prints every good record, pretty unless `--minify`. Bad lines are
+/// reported as `file:line: error` and make the exit status 1. `--check` only
+/// validates, `--slurp` collects the records into one array and `--explode`
+/// goes the other way, printing each element of a top-level array as a line.
+fn run_ndjson(args: &CliArgs) -> Result<i32, String> {
+    let file_path = positional_arg(args, 1, "a file path")?;
+    let input = get_file_as_string(file_path)?;
+    let config = format_config(args)?;
+    if args.has_flag("explode") {
+        let lines = minify_json_with(input.clone(), &config)
+            .and_then(|min_json| explode_json(&min_json))
+            .map_err(|err| diagnose_message(args, file_path, &input, err))?;
+        for line in lines {
+            println!("{line}");
+        }
+        return Ok(0);
+    }
+    let errors = if args.has_flag("check") {
+        validate_ndjson(&input)
+    } else if args.has_flag("slurp") {
+        match slurp_ndjson(&input, &config) {
+            Ok(array) => {
+                let text = if args.has_flag("minify") { array } else { prettify_json_with(array, &FormatConfig::default())? };
+                println!("{text}");
+                vec![]
+            },
+            Err(errors) => errors,
+        }
+    } else {
+        let (records, errors) = format_ndjson(&input, !args.has_flag("minify"), &config);
+        for record in records {
+            println!("{record}");
+        }
+        errors
+    };
+    let index = LineIndex::new(&input);
+    let color = use_color(args, std::io::stderr().is_terminal())?;
+    for err in &errors {
+        let line = index.line_span(err.line).unwrap_or_default();
+        // find the problem within the line, then point at it in the whole file
+        let mut found = parse_json_recovering(&input[line.start..line.end]).errors;
+        if found.is_empty() {
+            found.push(JsonError::new(err.message.clone(), err.span.unwrap_or(Span::new(0, line.len()))));
+        }
+        for mut error in found {
+            error.span = Span::new(error.span.start + line.start, error.span.end + line.start);
+            eprintln!("{}", render_diagnostic(&input, file_path, Level::Error, &error, color));
+        }
+    }
+    Ok(if errors.is_empty() { 0 } else { 1 })
+}
+
+/// `json-mini seq <file>`: formats each document of concatenated JSON or an
+/// RFC 7464 sequence (picked by whether the file has any 0x1E bytes) on its own,
+/// keeping the input's framing. `--boundaries` lists where each document is
+/// instead.
+fn run_seq(args: &CliArgs) -> Result<i32, String> {
+    let file_path = positional_arg(args, 1, "a file path")?;
+    let input = get_file_as_string(file_path)?;
+    let format = SequenceFormat::detect(&input);
+    let documents = parse_sequence(&input, format).map_err(|err| diagnose_sequence(args, file_path, &input, err))?;
+    if args.has_flag("boundaries") {
+        let index = LineIndex::new(&input);
+        for (i, doc) in documents.iter().enumerate() {
+            println!("{}: {}..{} ({}-{}) {}", i + 1, doc.span.start, doc.span.end,
+                index.line_col(doc.span.start), index.line_col(doc.span.end), doc.value.kind());
+        }
+        return Ok(0);
+    }
+    let config = format_config(args)?;
+    for doc in documents {
+        let min_json = minify_json_with(String::from(&input[doc.span.start..doc.span.end]), &config)?;
+        let text = if args.has_flag("minify") { min_json } else { prettify_json_with(min_json, &FormatConfig::default())? };
+        match format {
+            SequenceFormat::RecordSeparated => println!("{RECORD_SEPARATOR}{text}"),
+            SequenceFormat::Concatenated => println!("{text}"),
+        }
+    }
+    Ok(0)
+}
+
+/// `json-mini check <file>`: lists every syntax error in the file in one pass,
+/// exiting with 1 if there are any.
+fn run_check(args: &CliArgs) -> Result<i32, String> {
+    let file_path = positional_arg(args, 1, "a file path")?;
+    let input = get_file_as_string(file_path)?;
+    let errors = parse_json_recovering(&input).errors;
+    let color = use_color(args, std::io::stdout().is_terminal())?;
+    for err in &errors {
+        println!("{}", render_diagnostic(&input, file_path, Level::Error, err, color));
+    }
+    if errors.is_empty() {
+        println!("{file_path}: no problems found");
+        return Ok(0);
+    }
+    println!("{}: {} problem(s)", file_path, errors.len());
+    Ok(1)
+}
+
+/// `json-mini repair <file> [--in-place]`: fixes the usual almost-JSON mistakes
+/// and prints the result, listing each fix on stderr unless `--quiet`.
+fn run_repair(args: &CliArgs) -> Result<i32, String> {
+    let file_path = positional_arg(args, 1, "a file path")?;
+    let input = get_file_as_string(file_path)?;
+    let repaired = repair_json(&input).map_err(|err| diagnose_message(args, file_path, &input, err))?;
+    if !args.has_flag("quiet") {
+        let index = LineIndex::new(&input);
+        for fix in &repaired.fixes {
+            eprintln!("{}:{}: {}", file_path, index.line_col(fix.span.start), fix.description);
+        }
+    }
+    if args.has_flag("in-place") {
+        if !repaired.fixes.is_empty() {
+            write_atomically(file_path, &repaired.json)?;
+        }
+        return Ok(0);
+    }
+    print!("{}", repaired.json);
+    if !repaired.json.ends_with('\n') {
+        println!();
+    }
+    Ok(0)
+}
+
+/// `json-mini set <file> <pointer> <value>`: sets one value, adding it if the
+/// parent exists, and writes the file back. A value that reads as a JSON number,
+/// boolean, null or string keeps that type and anything else is stored as a
+/// string; `--json` takes any JSON value and `--string` always stores a string.
+fn run_set(args: &CliArgs) -> Result<i32, String> {
+    let file_path = positional_arg(args, 1, "a file path")?;
+    let pointer = positional_arg(args, 2, "a JSON Pointer")?;
+    let text = positional_arg(args, 3, "a value")?;
+    no_more_args(args, 4)?;
+    let value = if args.has_flag("string") {
+        JsonValue::String(String::from(text))
+    } else if args.has_flag("json") {
+        parse_json(text).map_err(|err| format!("Invalid --json value: {err}"))?
+    } else {
+        match parse_json(text) {
+            Ok(scalar) if !matches!(scalar, JsonValue::Array(_) | JsonValue::Object(_)) => scalar,
+            _ => JsonValue::String(String::from(text)),
+        }
+    };
+    let op = |doc: &JsonValue| match doc.pointer(pointer) {
+        Some(_) => PatchOp::Replace { path: String::from(pointer), value: value.clone() },
+        None => PatchOp::Add { path: String::from(pointer), value: value.clone() },
+    };
+    edit_file(args, file_path, op, |cst| cst.set(pointer, &value))
+}
+
+/// `json-mini del <file> <pointer>`: removes one member or element and writes
+/// the file back.
+fn run_del(args: &CliArgs) -> Result<i32, String> {
+    let file_path = positional_arg(args, 1, "a file path")?;
+    let pointer = positional_arg(args, 2, "a JSON Pointer")?;
+    no_more_args(args, 3)?;
+    edit_file(args, file_path, |_| PatchOp::Remove { path: String::from(pointer) }, |cst| cst.remove(pointer))
+}
+
+// Makes one edit to a file in place, or prints the result with `--stdout`. The
+// edit goes through the CST so the rest of the file keeps its layout; if that
+// doesn't give the same document as `op` on the parsed value, the file is
+// reformatted instead.
+fn edit_file(args: &CliArgs, file_path: &str, op: impl FnOnce(&JsonValue) -> PatchOp, edit: impl FnOnce(&mut Cst) -> Result<(), String>) -> Result<i32, String> {
+    let input = get_file_as_string(file_path)?;
+    // comments are kept by the tree, so it is what reads the file
+    let mut cst = Cst::parse(&input).map_err(|err| diagnose_commented(args, file_path, &input, err))?;
+    let mut expected = cst.to_value()?;
+    let op = op(&expected);
+    apply_op(&mut expected, &op).map_err(|err| format!("{file_path}: cannot {} {}: {err}", op.name(), op.path()))?;
+    let output = match edit(&mut cst) {
+        Ok(()) if cst.to_value().as_ref() == Ok(&expected) => cst.to_string(),
+        _ => {
+            eprintln!("json-mini: {file_path}: could not keep the original formatting, reformatting the file");
+            expected.to_pretty_string()? + "\n"
+        },
+    };
+    if args.has_flag("stdout") {
+        print!("{output}");
+        return Ok(0);
+    }
+    write_atomically(file_path, &output)?;
+    Ok(0)
+}
+
+// Writes to a temporary file next to `path` and renames it into place, so a
+// failed write leaves the old file as it was instead of truncated.
+fn write_atomically(path: &str, contents: &str) -> Result<(), String> {
+    let fail = |err: std::io::Error| format!("Failed to write '{path}': {err}");
+    // a symlink stays a symlink; its target is what gets replaced
+    let target = fs::canonicalize(path).map_err(fail)?;
+    let name = target.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let temp = target.with_file_name(format!(".{name}.{}.tmp", process::id()));
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::set_permissions(&temp, fs::metadata(&target)?.permissions())?;
+        fs::rename(&temp, &target)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result.map_err(fail)
+}
+
+/// `json-mini locate <file> <pointer | line[:col]>`: prints where the value at
+/// a JSON Pointer is written, or the pointer of what is written at a position,
+/// as `file:line:col: pointer`. `--offset=N` looks up a byte offset instead, and
+/// a line without a column means the first thing written on it.
+fn run_locate(args: &CliArgs) -> Result<i32, String> {
+    let file_path = positional_arg(args, 1, "a file path")?;
+    let input = get_file_as_string(file_path)?;
+    let index = PositionIndex::new(&input).map_err(|err| diagnose_message(args, file_path, &input, err))?;
+    let location = if let Some(offset) = args.flag_value("offset") {
+        let offset: usize = offset.parse().map_err(|_| format!("Invalid byte offset '{offset}'"))?;
+        index.at_offset(offset).ok_or_else(|| format!("{file_path}: no value at byte {offset}"))?
+    } else {
+        let target = positional_arg(args, 2, "a JSON Pointer or a line:col position")?;
+        if target.is_empty() || target.starts_with('/') {
+            parse_pointer(target)?;
+            index.locate(target).ok_or_else(|| format!("{file_path}: nothing at '{target}'"))?
+        } else {
+            let at = parse_line_col(&input, index.lines(), target)?;
+            index.at_line_col(at).ok_or_else(|| format!("{file_path}: no value at {target}"))?
+        }
+    };
+    println!("{}:{}: {}", file_path, index.lines().line_col(location.span().start), location.pointer);
+    Ok(0)
+}
+
+// "L:C", or "L" for the first non-blank character on line L
+fn parse_line_col(input: &str, lines: &LineIndex, target: &str) -> Result<LineCol, String> {
+    let invalid = || format!("Invalid position '{target}': expected a line number or line:col");
+    let (line, col) = target.split_once(':').map_or((target, None), |(line, col)| (line, Some(col)));
+    let line: usize = line.parse().map_err(|_| invalid())?;
+    let col = match col {
+        Some(col) => col.parse().map_err(|_| invalid())?,
+        None => {
+            let span = lines.line_span(line).ok_or_else(|| format!("Line {line} is past the end of the file"))?;
+            let text = &input[span.start..span.end];
+            text.chars().take_while(|c| c.is_whitespace()).count() + 1
+        },
+    };
+    Ok(LineCol { line, col })
+}
+
+/// `json-mini query <file> <jsonpath>`: prints the selected values as an array,
+/// or with `--paths` as `{"path", "value"}` objects.
+fn run_query(args: &CliArgs) -> Result<i32, String> {
+    let value = parse_file(args, positional_arg(args, 1, "a file path")?)?;
+    let matches = query_json_path(&value, positional_arg(args, 2, "a JSONPath query")?)?;
+    let items = matches.into_iter().map(|m| {
+        if !args.has_flag("paths") {
+            return m.value.clone();
+        }
+        let mut obj = JsonObject::new();
+        obj.insert(String::from("path"), JsonValue::from(m.path));
+        obj.insert(String::from("value"), m.value.clone());
+        JsonValue::Object(obj)
+    }).collect();
+    print_value(args, &JsonValue::Array(items))?;
+    Ok(0)
+}
+
+/// `json-mini merge <base> <overlay>...`: deep-merges the files left to right.
+/// Defaults follow RFC 7396; `--arrays=concat` and `--nulls=keep` change that.
+fn run_merge(args: &CliArgs) -> Result<i32, String> {
+    positional_arg(args, 1, "at least one file path")?;
+    let options = MergeOptions {
+        arrays: args.flag_value("arrays").map(ArrayMerge::from_name).transpose()?.unwrap_or_default(),
+        nulls: args.flag_value("nulls").map(NullMerge::from_name).transpose()?.unwrap_or_default(),
+    };
+    let layers = args.positional[1..].iter().map(|path| parse_file(args, path)).collect::<Result<Vec<_>, _>>()?;
+    if let Some(merged) = merge_all(&layers, &options) {
+        print_value(args, &merged)?;
+    }
+    Ok(0)
+}
+
+fn run_format(args: &CliArgs) -> Result<i32, String> {
+    let file_path = match args.positional.first() {
+        Some(path) => path,
+        None => return Err(String::from("Didn't get a file path")),
+    };
+    let input_json = get_file_as_string(file_path)?;
+
+    if args.has_flag("lint-duplicates") {
+        lint_duplicates(args, file_path, &input_json)?;
+        return Ok(0);
+    }
+    if let Some(policy) = args.flag_value("duplicate-keys") {
+        let pretty_json = format_with_policy(args, file_path, input_json, parse_duplicate_key_policy(policy)?)?;
+        println!("{}", pretty_json);
+        return Ok(0);
+    }
+
+    let config = format_config(args)?;
+    let min_json = minify_json_with(input_json.clone(), &config).map_err(|err| diagnose_message(args, file_path, &input_json, err))?;
+    if args.has_flag("minify") {
+        println!("{}", min_json);
+        return Ok(0);
+    }
+    let pretty_json = prettify_json_with(min_json, &FormatConfig::default()).map_err(|err| format!("Problem prettifying json {err}"))?;
+    println!("{}", pretty_json);
+    Ok(0)
+}
+
+fn main() -> Result<(), Error> {
+    let args = CliArgs::parse(env::args().skip(1)); // first val in env::args() is name of program
+    let result = match args.positional.first().map(String::as_str) {
+        Some("canon") => run_canon(&args),
+        Some("diff") => run_diff(&args),
+        Some("patch") => run_patch(&args),
+        Some("mkpatch") => run_mkpatch(&args),
+        Some("merge") => run_merge(&args),
+        Some("get") => run_get(&args),
+        Some("query") => run_query(&args),
+        Some("explode") => run_explode(&args),
+        Some("ndjson") => run_ndjson(&args),
+        Some("seq") => run_seq(&args),
+        Some("check") => run_check(&args),
+        Some("repair") => run_repair(&args),
+        Some("set") => run_set(&args),
+        Some("del") => run_del(&args),
+        Some("locate") => run_locate(&args),
+        _ => run_format(&args),
+    };
+    let status = result.unwrap_or_else(|err| {
+        eprintln!("json-mini: {err}");
 		process::exit(1);
     });
-    println!("{}", pretty_json);
+    if status != 0 {
+        process::exit(status);
+    }
 
     Ok(())
 }
-
@@ -0,0 +1,145 @@
+use crate::object::JsonObject;
+use crate::value::JsonValue;
+
+/// What to do when both sides of a merge hold an array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayMerge {
+    /// The overlay's array wins, as in RFC 7396.
+    #[default]
+    Replace,
+    /// The overlay's elements are appended to the base's.
+    Concat,
+}
+
+/// What a `null` in the overlay means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullMerge {
+    /// Removes the member, as in RFC 7396.
+    #[default]
+    Delete,
+    /// Sets the member to a literal `null`.
+    Keep,
+}
+
+impl ArrayMerge {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "replace" => Ok(ArrayMerge::Replace),
+            "concat" => Ok(ArrayMerge::Concat),
+            _ => Err(format!("Unknown array merge '{}', expected replace or concat", name)),
+        }
+    }
+}
+
+impl NullMerge {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "delete" => Ok(NullMerge::Delete),
+            "keep" => Ok(NullMerge::Keep),
+            _ => Err(format!("Unknown null merge '{}', expected delete or keep", name)),
+        }
+    }
+}
+
+/// The default options give exactly RFC 7396 semantics.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MergeOptions {
+    pub arrays: ArrayMerge,
+    pub nulls: NullMerge,
+}
+
+/// Applies `patch` to `target` as a JSON Merge Patch (RFC 7396).
+pub fn merge_patch(target: &JsonValue, patch: &JsonValue) -> JsonValue {
+    let mut merged = target.clone();
+    deep_merge(&mut merged, patch, &MergeOptions::default());
+    merged
+}
+
+/// Merges `overlay` into `base`: objects merge member by member, anything else
+/// in the overlay replaces what is in the base, except as `options` say.
+pub fn deep_merge(base: &mut JsonValue, overlay: &JsonValue, options: &MergeOptions) {
+    match (base, overlay) {
+        (JsonValue::Object(base_obj), JsonValue::Object(overlay_obj)) => merge_members(base_obj, overlay_obj, options),
+        (JsonValue::Array(base_arr), JsonValue::Array(overlay_arr)) if options.arrays == ArrayMerge::Concat => {
+            base_arr.extend(overlay_arr.iter().cloned());
+        },
+        (base, JsonValue::Object(overlay_obj)) => {
+            let mut obj = JsonObject::new();
+            merge_members(&mut obj, overlay_obj, options);
+            *base = JsonValue::Object(obj);
+        },
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+fn merge_members(base: &mut JsonObject, overlay: &JsonObject, options: &MergeOptions) {
+    for (key, value) in overlay.iter() {
+        if value.is_null() && options.nulls == NullMerge::Delete {
+            base.remove(key);
+        } else if let Some(existing) = base.get_mut(key) {
+            deep_merge(existing, value, options);
+        } else {
+            // merging into nothing still strips nulls from nested objects
+            let mut fresh = JsonValue::Null;
+            deep_merge(&mut fresh, value, options);
+            base.insert(key.clone(), fresh);
+        }
+    }
+}
+
+/// Folds `layers` left to right, so later layers win.
+pub fn merge_all<'a>(layers: impl IntoIterator<Item = &'a JsonValue>, options: &MergeOptions) -> Option<JsonValue> {
+    let mut layers = layers.into_iter();
+    let mut merged = layers.next()?.clone();
+    for layer in layers {
+        deep_merge(&mut merged, layer, options);
+    }
+    Some(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_all, merge_patch, ArrayMerge, MergeOptions, NullMerge};
+    use crate::parse_json;
+
+    #[test]
+    fn test_merge_patch_rfc7396_examples() {
+        // Appendix A of RFC 7396
+        let cases = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (r#"{"a":{"b":"c"}}"#, r#"{"a":{"b":"d","c":null}}"#, r#"{"a":{"b":"d"}}"#),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, r#"null"#, r#"null"#),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            (r#"{}"#, r#"{"a":{"bb":{"ccc":null}}}"#, r#"{"a":{"bb":{}}}"#),
+        ];
+        for (target, patch, expected) in cases {
+            let merged = merge_patch(&parse_json(target).unwrap(), &parse_json(patch).unwrap());
+            assert_eq!(merged.to_json_string(), expected, "{} + {}", target, patch);
+        }
+    }
+
+    #[test]
+    fn test_merge_all_options() {
+        let layers = [
+            parse_json(r#"{"hosts":["a"],"debug":true,"db":{"port":5432,"user":"app"}}"#).unwrap(),
+            parse_json(r#"{"hosts":["b","c"],"db":{"user":null}}"#).unwrap(),
+            parse_json(r#"{"debug":null}"#).unwrap(),
+        ];
+        let merged = merge_all(&layers, &MergeOptions::default()).unwrap();
+        assert_eq!(merged.to_json_string(), r#"{"hosts":["b","c"],"db":{"port":5432}}"#);
+        let options = MergeOptions { arrays: ArrayMerge::Concat, nulls: NullMerge::Keep };
+        let merged = merge_all(&layers, &options).unwrap();
+        assert_eq!(merged.to_json_string(), r#"{"hosts":["a","b","c"],"debug":null,"db":{"port":5432,"user":null}}"#);
+        assert!(merge_all(&[], &options).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+use crate::parser::parse_json;
+use crate::position::Span;
+use crate::stream::iter_array_at;
+use crate::{minify_json_with, prettify_json_with, FormatConfig};
+
+/// A problem with one record of newline-delimited JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineError {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+    /// Where in the line the problem is, when the parser could tell.
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// The records of `input` with their line numbers. Blank lines are skipped and a
+/// trailing `\r` is dropped, so CRLF files work too.
+pub fn ndjson_lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.strip_suffix('\r').unwrap_or(line)))
+        .filter(|(_, line)| !line.trim().is_empty())
+}
+
+fn check_line(line: usize, text: &str) -> Result<(), LineError> {
+    parse_json(text).map(|_| ()).map_err(|err| LineError { line, message: err.to_string(), span: Some(err.span) })
+}
+
+/// Checks every record on its own, reporting all bad lines rather than the first.
+pub fn validate_ndjson(input: &str) -> Vec<LineError> {
+    ndjson_lines(input).filter_map(|(line, text)| check_line(line, text).err()).collect()
+}
+
+/// Minifies (or pretty-prints) each valid record. Bad lines are left out of the
+/// output and reported instead.
+pub fn format_ndjson(input: &str, pretty: bool, config: &FormatConfig) -> (Vec<String>, Vec<LineError>) {
+    let mut records = vec![];
+    let mut errors = vec![];
+    for (line, text) in ndjson_lines(input) {
+        let formatted = check_line(line, text).and_then(|_| {
+            let min_json = minify_json_with(String::from(text), config);
+            let result = if pretty { min_json.and_then(|min| prettify_json_with(min, &FormatConfig::default())) } else { min_json };
+            result.map_err(|message| LineError { line, message, span: None })
+        });
+        match formatted {
+            Ok(record) => records.push(record),
+            Err(err) => errors.push(err),
+        }
+    }
+    (records, errors)
+}
+
+/// Collects every record into one minified array, or every bad line if any.
+pub fn slurp_ndjson(input: &str, config: &FormatConfig) -> Result<String, Vec<LineError>> {
+    let (records, errors) = format_ndjson(input, false, config);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(format!("[{}]", records.join(",")))
+}
+
+/// Splits a top-level array into one minified line per element. Each line is
+/// the element's source without its whitespace, so `"\u00e9"` stays escaped.
+pub fn explode_json(input: &str) -> Result<Vec<String>, String> {
+    let mut elements = iter_array_at(input.as_bytes(), "")?;
+    let mut lines = vec![];
+    while let Some(element) = elements.next_text() {
+        lines.push(element?);
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{explode_json, format_ndjson, slurp_ndjson, validate_ndjson};
+    use crate::{FormatConfig, KeyOrder};
+
+    const LOG: &str = "{\"level\": \"info\", \"msg\": \"started\"}\r\n\n{\"level\":\"warn\",\n{\"b\": 2, \"a\": [1, 2]}\nnull\n";
+
+    #[test]
+    fn test_validate_ndjson() {
+        let errors = validate_ndjson(LOG);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 3);
+        assert!(errors[0].to_string().starts_with("line 3: "));
+        assert!(validate_ndjson("1\n\"two\"\n[3]").is_empty());
+    }
+
+    #[test]
+    fn test_format_ndjson() {
+        let config = FormatConfig { sort_keys: Some(KeyOrder::Lexicographic), ..Default::default() };
+        let (records, errors) = format_ndjson(LOG, false, &config);
+        assert_eq!(records, [r#"{"level":"info","msg":"started"}"#, r#"{"a":[1,2],"b":2}"#, "null"]);
+        assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), [3]);
+        let (pretty, _) = format_ndjson("{\"a\":1}", true, &FormatConfig::default());
+        assert_eq!(pretty, ["{\n \"a\":1\n}"]);
+    }
+
+    #[test]
+    fn test_slurp_and_explode() {
+        let input = "{\"a\":1}\n[2, 3]\n\"x\"\n";
+        let slurped = slurp_ndjson(input, &FormatConfig::default()).unwrap();
+        assert_eq!(slurped, r#"[{"a":1},[2,3],"x"]"#);
+        assert_eq!(explode_json(&slurped).unwrap().join("\n") + "\n", "{\"a\":1}\n[2,3]\n\"x\"\n");
+        let errors = slurp_ndjson("1\n{", &FormatConfig::default()).unwrap_err();
+        assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), [2]);
+        assert!(explode_json("{\"a\":1}").is_err());
+        assert_eq!(explode_json("[1.50, {\"a\": \"\\u00e9\"}]").unwrap(), ["1.50", "{\"a\":\"\\u00e9\"}"]);
+        assert!(explode_json("[1,{\"a\":\"\\u00e9\"}] 5").is_err());
+        assert_eq!(explode_json(" [] ").unwrap(), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,290 @@
+use std::fmt;
+
+/// A JSON number kept exactly as it was written.
+///
+/// Nothing is converted until one of the `as_*` accessors asks for it, so values
+/// like `8527553402961357` or `0.1000000000000000055511151231257827` survive a
+/// parse/serialize round trip untouched. Each accessor reports when the number
+/// does not fit the requested type instead of silently rounding or wrapping.
+#[derive(Debug, Clone)]
+pub struct JsonNumber {
+    lexeme: String,
+}
+
+/// Exact decimal form of a number: `(-1)^negative * digits * 10^exponent`, with
+/// no leading or trailing zeros in `digits` (zero is the empty string).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+impl JsonNumber {
+    /// Validates `lexeme` against the JSON number grammar.
+    pub fn parse(lexeme: &str) -> Result<Self, String> {
+        if !is_json_number(lexeme) {
+            return Err(format!("Invalid number '{}'", lexeme));
+        }
+        Ok(Self { lexeme: String::from(lexeme) })
+    }
+
+    /// The shortest text that reads back as `value`, or `None` for NaN and infinities.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        // `{}` never uses an exponent and `{:e}` always does; each is the shortest
+        // round-tripping form in its notation, so take the shorter of the two
+        let plain = format!("{}", value);
+        let scientific = format!("{:e}", value);
+        let lexeme = if scientific.len() < plain.len() { scientific } else { plain };
+        Some(Self { lexeme })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.lexeme
+    }
+
+    /// True if the number was written without a fraction or exponent.
+    pub fn is_integer_literal(&self) -> bool {
+        !self.lexeme.contains(['.', 'e', 'E'])
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.decimal().negative
+    }
+
+    pub fn as_u64(&self) -> Result<u64, String> {
+        let value = self.as_i128()?;
+        u64::try_from(value).map_err(|_| format!("{} does not fit in u64", self.lexeme))
+    }
+
+    pub fn as_i64(&self) -> Result<i64, String> {
+        let value = self.as_i128()?;
+        i64::try_from(value).map_err(|_| format!("{} does not fit in i64", self.lexeme))
+    }
+
+    /// Integer value of the number. `1.0` and `1e3` are integers; `1.5` is not.
+    pub fn as_i128(&self) -> Result<i128, String> {
+        let decimal = self.decimal();
+        if decimal.exponent < 0 {
+            return Err(format!("{} is not an integer", self.lexeme));
+        }
+        // i128 holds at most 39 digits, anything longer overflows for sure
+        if decimal.digits.len() as i64 + decimal.exponent > 39 {
+            return Err(format!("{} does not fit in i128", self.lexeme));
+        }
+        let mut magnitude: u128 = 0;
+        let zeros = std::iter::repeat_n(0u32, decimal.exponent as usize);
+        for digit in decimal.digits.chars().map(|c| c as u32 - '0' as u32).chain(zeros) {
+            magnitude = magnitude.checked_mul(10)
+                .and_then(|m| m.checked_add(digit as u128))
+                .ok_or_else(|| format!("{} does not fit in i128", self.lexeme))?;
+        }
+        if decimal.negative {
+            0i128.checked_sub_unsigned(magnitude)
+                .ok_or_else(|| format!("{} does not fit in i128", self.lexeme))
+        } else {
+            i128::try_from(magnitude).map_err(|_| format!("{} does not fit in i128", self.lexeme))
+        }
+    }
+
+    /// Nearest f64. Fails only if the number is too large to be finite; use
+    /// `round_trips_f64` to find out whether precision would be lost.
+    pub fn as_f64(&self) -> Result<f64, String> {
+        let value: f64 = self.lexeme.parse().map_err(|_| format!("Invalid number '{}'", self.lexeme))?;
+        if value.is_finite() { Ok(value) } else { Err(format!("{} overflows f64", self.lexeme)) }
+    }
+
+    /// True if converting to f64 and back yields the same numeric value, i.e. an
+    /// f64-based consumer would not corrupt the number.
+    pub fn round_trips_f64(&self) -> bool {
+        match self.as_f64() {
+            Ok(value) => JsonNumber::from_f64(value).is_some_and(|back| back.decimal() == self.decimal()),
+            Err(_) => false,
+        }
+    }
+
+    fn decimal(&self) -> Decimal {
+        let (negative, unsigned) = match self.lexeme.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, self.lexeme.as_str()),
+        };
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(i) => (&unsigned[..i], parse_exponent(&unsigned[i + 1..])),
+            None => (unsigned, 0),
+        };
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = String::from(int_part) + frac_part;
+        let trimmed = all_digits.trim_start_matches('0');
+        let significant = trimmed.trim_end_matches('0');
+        if significant.is_empty() {
+            return Decimal { negative: false, digits: String::new(), exponent: 0 };
+        }
+        let trailing_zeros = (trimmed.len() - significant.len()) as i64;
+        Decimal {
+            negative,
+            digits: String::from(significant),
+            exponent: exponent.saturating_sub(frac_part.len() as i64).saturating_add(trailing_zeros),
+        }
+    }
+}
+
+// exponents beyond the i64 range are clamped, they overflow every accessor anyway
+fn parse_exponent(text: &str) -> i64 {
+    let (negative, digits) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let magnitude = digits.parse::<i64>().unwrap_or(i64::MAX / 2);
+    if negative { -magnitude } else { magnitude }
+}
+
+/// Checks `text` against the number production of RFC 8259.
+pub fn is_json_number(text: &str) -> bool {
+    scan_number(text.as_bytes()) == Ok(text.len())
+}
+
+// number grammar from RFC 8259: -?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?
+// Gives the length of the number at the start of `bytes`, or why there isn't
+// one there. The lexer, the streaming reader and `is_json_number` all go by this.
+pub(crate) fn scan_number(bytes: &[u8]) -> Result<usize, &'static str> {
+    let digits_from = |i: usize| i + bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let is_digit = |i: usize| bytes.get(i).is_some_and(u8::is_ascii_digit);
+    let mut end = usize::from(bytes.first() == Some(&b'-'));
+    match bytes.get(end) {
+        Some(b'0') if is_digit(end + 1) => return Err("leading zeros are not allowed"),
+        Some(b'0'..=b'9') => end = digits_from(end),
+        _ => return Err("expected a digit"),
+    }
+    if bytes.get(end) == Some(&b'.') {
+        if !is_digit(end + 1) {
+            return Err("expected a digit after '.'");
+        }
+        end = digits_from(end + 1);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        end += 1;
+        if matches!(bytes.get(end), Some(b'+' | b'-')) {
+            end += 1;
+        }
+        if !is_digit(end) {
+            return Err("expected a digit in the exponent");
+        }
+        end = digits_from(end);
+    }
+    Ok(end)
+}
+
+/// Numbers compare by exact value, so `1.0 == 1` and `1e2 == 100`.
+impl PartialEq for JsonNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.lexeme == other.lexeme || self.decimal() == other.decimal()
+    }
+}
+
+impl fmt::Display for JsonNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.lexeme)
+    }
+}
+
+impl From<i64> for JsonNumber {
+    fn from(value: i64) -> Self {
+        Self { lexeme: value.to_string() }
+    }
+}
+
+impl From<u64> for JsonNumber {
+    fn from(value: u64) -> Self {
+        Self { lexeme: value.to_string() }
+    }
+}
+
+impl From<i128> for JsonNumber {
+    fn from(value: i128) -> Self {
+        Self { lexeme: value.to_string() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::{is_json_number, JsonNumber};
+    use crate::{minify_json, parse_json, prettify_json};
+
+    fn num(lexeme: &str) -> JsonNumber {
+        JsonNumber::parse(lexeme).unwrap()
+    }
+
+    #[test]
+    fn test_is_json_number() {
+        for valid in ["0", "-0", "12", "-12.5", "1e10", "1E+2", "1.5e-3", "8527553402961357"] {
+            assert!(is_json_number(valid), "{}", valid);
+        }
+        for invalid in ["", "-", "01", "1.", ".5", "1e", "1e+", "+1", "0x10", "1.5.2", "NaN"] {
+            assert!(!is_json_number(invalid), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_integer_accessors() {
+        assert_eq!(num("8527553402961357").as_u64(), Ok(8527553402961357));
+        assert_eq!(num("-9223372036854775808").as_i64(), Ok(i64::MIN));
+        assert_eq!(num("18446744073709551616").as_u64(), Err(String::from("18446744073709551616 does not fit in u64")));
+        assert_eq!(num("18446744073709551616").as_i128(), Ok(18446744073709551616));
+        assert_eq!(num("-1").as_u64(), Err(String::from("-1 does not fit in u64")));
+        assert_eq!(num("1.5").as_i64(), Err(String::from("1.5 is not an integer")));
+        assert_eq!(num("2.50e1").as_i64(), Ok(25));
+        assert_eq!(num("1e40").as_i128(), Err(String::from("1e40 does not fit in i128")));
+        assert_eq!(num("0.000e999999999999999999999").as_u64(), Ok(0));
+    }
+
+    #[test]
+    fn test_float_accessors() {
+        assert_eq!(num("-2.39").as_f64(), Ok(-2.39));
+        assert_eq!(num("1e400").as_f64(), Err(String::from("1e400 overflows f64")));
+        assert!(num("9007199254740992").round_trips_f64());
+        assert!(!num("9007199254740993").round_trips_f64());
+        assert!(num("0.1").round_trips_f64());
+        assert!(num("1e300").round_trips_f64());
+        let text = |value: f64| JsonNumber::from_f64(value).unwrap().as_str().to_owned();
+        assert_eq!(text(1e300), "1e300");
+        assert_eq!(text(-2.5e-12), "-2.5e-12");
+        assert_eq!(text(1234.5), "1234.5");
+        assert_eq!(text(0.0), "0");
+        assert_eq!(JsonNumber::from_f64(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_numeric_equality() {
+        assert_eq!(num("1.0"), num("1"));
+        assert_eq!(num("1e2"), num("100"));
+        assert_eq!(num("-0"), num("0.0"));
+        assert_ne!(num("9007199254740993"), num("9007199254740992"));
+    }
+
+    #[test]
+    fn test_formatters_keep_number_text() {
+        let input = String::from(r#"[8527553402961357,-0.0,1.50E+10,1e-7,100000000000000000000000]"#);
+        assert_eq!(minify_json(input.clone()).unwrap(), input);
+        let pretty = prettify_json(input.clone()).unwrap();
+        assert_eq!(minify_json(pretty).unwrap(), input);
+        assert_eq!(parse_json(&input).unwrap().to_json_string(), input);
+    }
+
+    #[test]
+    fn test_massive_arr_numbers_lossless() {
+        let input = fs::read_to_string("testfiles/massive_arr_test.json").expect("failed to read 'testfiles/massive_arr_test.json'");
+        let value = parse_json(&input).unwrap();
+        let tx_indexes = value.get("txIndexes").and_then(|v| v.as_array()).unwrap();
+        assert!(input.contains("8527553402961357"));
+        for tx in tx_indexes {
+            let number = tx.as_number().unwrap();
+            assert_eq!(number.as_u64().unwrap().to_string(), number.as_str());
+        }
+        assert_eq!(value.to_json_string(), minify_json(input).unwrap());
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use crate::value::JsonValue;
+
+/// What to do when an object literal repeats a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// Reject the document.
+    Error,
+    /// Keep the first value seen for the key and drop the later ones.
+    KeepFirst,
+    /// Later values overwrite earlier ones. The key keeps the position of its first occurrence.
+    #[default]
+    KeepLast,
+    /// Same as `KeepLast`, but the parser reports every repeat so it can be shown to the user.
+    Warn,
+}
+
+/// The map behind `JsonValue::Object`.
+///
+/// Members are kept in insertion order so that a parsed document is written back
+/// out with its keys exactly where they were, and a side index gives O(1) lookup
+/// by key. An object created with `JsonObject::sorted()` keeps its members ordered
+/// by key instead.
+#[derive(Debug, Clone, Default)]
+pub struct JsonObject {
+    entries: Vec<(String, JsonValue)>,
+    index: HashMap<String, usize>,
+    sorted: bool,
+}
+
+impl JsonObject {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity(capacity),
+            sorted: false,
+        }
+    }
+
+    /// An object that keeps its members sorted by key on every insert.
+    pub fn sorted() -> Self {
+        Self { sorted: true, ..Default::default() }
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        self.sorted
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
+        match self.index.get(key) {
+            Some(&i) => Some(&mut self.entries[i].1),
+            None => None,
+        }
+    }
+
+    /// Position of `key` in iteration order.
+    pub fn index_of(&self, key: &str) -> Option<usize> {
+        self.index.get(key).copied()
+    }
+
+    pub fn get_index(&self, i: usize) -> Option<(&String, &JsonValue)> {
+        self.entries.get(i).map(|(k, v)| (k, v))
+    }
+
+    /// Inserts a member, returning the previous value if the key was already present.
+    /// An existing key keeps its position; a new key goes to the end (or to its
+    /// sorted position for a sorted object).
+    pub fn insert(&mut self, key: String, value: JsonValue) -> Option<JsonValue> {
+        if let Some(&i) = self.index.get(&key) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+        if self.sorted {
+            let at = self.entries.partition_point(|(k, _)| *k < key);
+            self.entries.insert(at, (key, value));
+            self.reindex_from(at);
+        } else {
+            self.index.insert(key.clone(), self.entries.len());
+            self.entries.push((key, value));
+        }
+        None
+    }
+
+    /// Inserts a member while parsing, resolving a repeated key with `policy`.
+    pub fn insert_with_policy(&mut self, key: String, value: JsonValue, policy: DuplicateKeyPolicy) -> Result<(), String> {
+        if self.contains_key(&key) {
+            match policy {
+                DuplicateKeyPolicy::Error => return Err(format!("Duplicate key {} in object", crate::value::escape_json_string(&key))),
+                DuplicateKeyPolicy::KeepFirst => return Ok(()),
+                DuplicateKeyPolicy::KeepLast | DuplicateKeyPolicy::Warn => (),
+            }
+        }
+        self.insert(key, value);
+        Ok(())
+    }
+
+    /// Removes a member, shifting the ones after it down so order is preserved.
+    /// This is O(n) in the number of members.
+    pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        self.reindex_from(i);
+        Some(value)
+    }
+
+    /// Renames a member in place, keeping its position. Fails if `to` is already taken.
+    pub fn rename(&mut self, from: &str, to: String) -> Result<(), String> {
+        if from == to {
+            return Ok(());
+        }
+        if self.contains_key(&to) {
+            return Err(format!("Key {} already exists", crate::value::escape_json_string(&to)));
+        }
+        let i = match self.index.remove(from) {
+            Some(i) => i,
+            None => return Err(format!("No such key {}", crate::value::escape_json_string(from))),
+        };
+        if self.sorted {
+            let (_, value) = self.entries.remove(i);
+            self.reindex_from(i);
+            self.insert(to, value);
+        } else {
+            self.index.insert(to.clone(), i);
+            self.entries[i].0 = to;
+        }
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &JsonValue)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut JsonValue)> {
+        self.entries.iter_mut().map(|(k, v)| (&*k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &JsonValue> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    /// Reorders the members by key. This does not make later inserts sorted; use
+    /// `JsonObject::sorted()` for that.
+    pub fn sort_keys(&mut self) {
+        self.sort_by(|a, b| a.cmp(b));
+    }
+
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&str, &str) -> std::cmp::Ordering,
+    {
+        self.entries.sort_by(|(a, _), (b, _)| compare(a, b));
+        self.reindex_from(0);
+    }
+
+    fn reindex_from(&mut self, start: usize) {
+        for (i, (key, _)) in self.entries.iter().enumerate().skip(start) {
+            match self.index.get_mut(key) {
+                Some(slot) => *slot = i,
+                None => { self.index.insert(key.clone(), i); },
+            }
+        }
+    }
+}
+
+/// Objects compare as maps: same keys with equal values, regardless of order.
+impl PartialEq for JsonObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl FromIterator<(String, JsonValue)> for JsonObject {
+    fn from_iter<I: IntoIterator<Item = (String, JsonValue)>>(iter: I) -> Self {
+        let mut object = JsonObject::new();
+        for (key, value) in iter {
+            object.insert(key, value);
+        }
+        object
+    }
+}
+
+impl IntoIterator for JsonObject {
+    type Item = (String, JsonValue);
+    type IntoIter = std::vec::IntoIter<(String, JsonValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DuplicateKeyPolicy, JsonObject};
+    use crate::number::JsonNumber;
+    use crate::value::JsonValue;
+
+    fn num(n: &str) -> JsonValue {
+        JsonValue::Number(JsonNumber::parse(n).unwrap())
+    }
+
+    #[test]
+    fn test_insertion_order_preserved() {
+        let mut object = JsonObject::new();
+        object.insert(String::from("zeta"), num("1"));
+        object.insert(String::from("alpha"), num("2"));
+        object.insert(String::from("mid"), num("3"));
+        let keys: Vec<&String> = object.keys().collect();
+        assert_eq!(keys, vec!["zeta", "alpha", "mid"]);
+        assert_eq!(object.get("alpha"), Some(&num("2")));
+    }
+
+    #[test]
+    fn test_insert_existing_keeps_position() {
+        let mut object = JsonObject::new();
+        object.insert(String::from("a"), num("1"));
+        object.insert(String::from("b"), num("2"));
+        assert_eq!(object.insert(String::from("a"), num("3")), Some(num("1")));
+        let keys: Vec<&String> = object.keys().collect();
+        assert_eq!(keys, vec!["a", "b"]);
+        assert_eq!(object.get("a"), Some(&num("3")));
+    }
+
+    #[test]
+    fn test_remove_reindexes() {
+        let mut object: JsonObject = vec![
+            (String::from("a"), num("1")),
+            (String::from("b"), num("2")),
+            (String::from("c"), num("3")),
+        ].into_iter().collect();
+        assert_eq!(object.remove("a"), Some(num("1")));
+        assert_eq!(object.index_of("c"), Some(1));
+        assert_eq!(object.get("c"), Some(&num("3")));
+        assert_eq!(object.remove("a"), None);
+    }
+
+    #[test]
+    fn test_sorted_mode() {
+        let mut object = JsonObject::sorted();
+        object.insert(String::from("b"), num("1"));
+        object.insert(String::from("c"), num("2"));
+        object.insert(String::from("a"), num("3"));
+        let keys: Vec<&String> = object.keys().collect();
+        assert_eq!(keys, vec!["a", "b", "c"]);
+        assert_eq!(object.get("c"), Some(&num("2")));
+    }
+
+    #[test]
+    fn test_duplicate_policies() {
+        let mut object = JsonObject::new();
+        object.insert(String::from("a"), num("1"));
+        assert!(object.insert_with_policy(String::from("a"), num("2"), DuplicateKeyPolicy::Error).is_err());
+        object.insert_with_policy(String::from("a"), num("2"), DuplicateKeyPolicy::KeepFirst).unwrap();
+        assert_eq!(object.get("a"), Some(&num("1")));
+        object.insert_with_policy(String::from("a"), num("2"), DuplicateKeyPolicy::KeepLast).unwrap();
+        assert_eq!(object.get("a"), Some(&num("2")));
+    }
+
+    #[test]
+    fn test_eq_ignores_order() {
+        let a: JsonObject = vec![(String::from("x"), num("1")), (String::from("y"), num("2"))].into_iter().collect();
+        let b: JsonObject = vec![(String::from("y"), num("2")), (String::from("x"), num("1"))].into_iter().collect();
+        assert_eq!(a, b);
+    }
+}
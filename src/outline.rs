@@ -0,0 +1,137 @@
+use crate::cst::{Cst, CstNode};
+use crate::position::{LineIndex, Span};
+use crate::Token;
+
+/// A member or element of a document, with the ones nested in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineEntry {
+    /// The member's key, decoded; `None` for array elements.
+    pub key: Option<String>,
+    /// Where the entry is among its siblings, counting from 0.
+    pub index: usize,
+    /// The value's type, named like `JsonValue::kind`.
+    pub kind: &'static str,
+    /// The key of a member; the value of an element.
+    pub name_span: Span,
+    /// The value itself.
+    pub value_span: Span,
+    /// From the key, if any, to the end of the value.
+    pub span: Span,
+    /// First and last line of `span`, counting from 1.
+    pub start_line: usize,
+    pub end_line: usize,
+    pub children: Vec<OutlineEntry>,
+}
+
+impl OutlineEntry {
+    /// The key, or the index for array elements.
+    pub fn name(&self) -> String {
+        self.key.clone().unwrap_or_else(|| self.index.to_string())
+    }
+}
+
+/// An object or array that a viewer can collapse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldingRange {
+    /// `"object"` or `"array"`.
+    pub kind: &'static str,
+    /// From the opening to the closing bracket.
+    pub span: Span,
+    /// The lines of the brackets, counting from 1.
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// The members and elements of the root value, nested as they are in the
+/// document. A root that is not an object or array has no outline.
+pub fn outline(input: &str) -> Result<Vec<OutlineEntry>, String> {
+    let cst = Cst::parse(input)?;
+    Ok(entries(cst.root(), &LineIndex::new(input)))
+}
+
+/// Every object and array in the document, outermost first, including ones
+/// written on a single line.
+pub fn folding_ranges(input: &str) -> Result<Vec<FoldingRange>, String> {
+    let cst = Cst::parse(input)?;
+    let mut folds = vec![];
+    collect_folds(cst.root(), &LineIndex::new(input), &mut folds);
+    Ok(folds)
+}
+
+fn entries(node: &CstNode, lines: &LineIndex) -> Vec<OutlineEntry> {
+    let CstNode::Container { items, .. } = node else { return vec![] };
+    items.iter().enumerate().map(|(index, item)| {
+        let name_span = item.key.as_ref().map_or(item.value.span(), |(key, _)| key.span);
+        let span = name_span.to(item.value.span());
+        OutlineEntry {
+            key: item.key_text(),
+            index,
+            kind: kind(&item.value),
+            name_span,
+            value_span: item.value.span(),
+            span,
+            start_line: lines.line_col(span.start).line,
+            end_line: lines.line_col(span.end).line,
+            children: entries(&item.value, lines),
+        }
+    }).collect()
+}
+
+fn collect_folds(node: &CstNode, lines: &LineIndex, folds: &mut Vec<FoldingRange>) {
+    let CstNode::Container { items, .. } = node else { return };
+    let span = node.span();
+    folds.push(FoldingRange {
+        kind: kind(node),
+        span,
+        start_line: lines.line_col(span.start).line,
+        end_line: lines.line_col(span.end).line,
+    });
+    for item in items {
+        collect_folds(&item.value, lines, folds);
+    }
+}
+
+fn kind(node: &CstNode) -> &'static str {
+    match node {
+        CstNode::Container { .. } if node.is_object() => "object",
+        CstNode::Container { .. } => "array",
+        CstNode::Scalar(token) => match token.token {
+            Token::StringLiteral(_) => "string",
+            Token::NumericLiteral(_) => "number",
+            Token::BoolLiteral(_) => "boolean",
+            _ => "null",
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{folding_ranges, outline};
+    use crate::position::Span;
+
+    #[test]
+    fn test_outline() {
+        let input = "{\n \"a\":[1,\n  {\"b\":null}],\n \"c\\u0021\":\"x\"\n}";
+        let entries = outline(input).unwrap();
+        assert_eq!(entries.len(), 2);
+        let a = &entries[0];
+        assert_eq!((a.name(), a.kind, a.start_line, a.end_line), (String::from("a"), "array", 2, 3));
+        assert_eq!(&input[a.span.start..a.span.end], "\"a\":[1,\n  {\"b\":null}]");
+        assert_eq!(a.children[1].name(), "1");
+        assert_eq!(a.children[1].key, None);
+        assert_eq!(a.children[1].children[0].kind, "null");
+        assert_eq!(entries[1].key.as_deref(), Some("c!"));
+        assert_eq!(entries[1].name_span, Span::new(27, 36));
+        assert_eq!(entries[1].value_span, Span::new(37, 40));
+        assert!(outline("42").unwrap().is_empty());
+        assert!(outline("[1,").is_err());
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        let folds = folding_ranges("{\n \"a\":[1,\n  {\"b\":{}}]\n}").unwrap();
+        let summary: Vec<_> = folds.iter().map(|fold| (fold.kind, fold.start_line, fold.end_line)).collect();
+        assert_eq!(summary, [("object", 1, 4), ("array", 2, 3), ("object", 3, 3), ("object", 3, 3)]);
+        assert_eq!(folds[1].span, Span::new(7, 22));
+    }
+}
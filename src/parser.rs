@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use crate::error::JsonError;
+use crate::number::JsonNumber;
+use crate::object::{DuplicateKeyPolicy, JsonObject};
+use crate::position::Span;
+use crate::value::{escape_json_string, unescape_json_string, JsonValue};
+use crate::{JSONLexer, Token, IGNORE_WS};
+
+/// How deeply arrays and objects may nest by default. The parsers recurse once
+/// per level, so without a limit a few kilobytes of `[` overflow the stack.
+pub const MAX_DEPTH: usize = 512;
+
+/// Knobs for turning a token stream into a `JsonValue`.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    pub duplicate_keys: DuplicateKeyPolicy,
+    /// Build every object with `JsonObject::sorted()` instead of keeping source order.
+    pub sorted_objects: bool,
+    /// Containers nested deeper than this are an error.
+    pub max_depth: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions { duplicate_keys: DuplicateKeyPolicy::default(), sorted_objects: false, max_depth: MAX_DEPTH }
+    }
+}
+
+/// The error for a container nested deeper than `max_depth`.
+pub(crate) fn too_deep(max_depth: usize) -> String {
+    format!("Arrays and objects are nested more than {} deep", max_depth)
+}
+
+/// A key that appeared more than once in the same object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKey {
+    pub key: String,
+    /// Where the key was first defined.
+    pub first: Span,
+    /// Where it was defined again.
+    pub duplicate: Span,
+}
+
+/// Recursive descent parser over the tokens produced by `JSONLexer`.
+#[derive(Debug, Default)]
+pub struct JSONParser {
+    pub tokens: Vec<Token>,
+    pub spans: Vec<Span>,
+    pub pos: usize,
+    pub options: ParseOptions,
+    /// Every repeated key seen so far, whatever the duplicate key policy. With
+    /// `DuplicateKeyPolicy::Error` the last one is the repeat that failed the parse.
+    pub duplicates: Vec<DuplicateKey>,
+    /// How many containers the parser is inside.
+    depth: usize,
+}
+
+impl JSONParser {
+    pub fn from(tokens: Vec<Token>, options: ParseOptions) -> Self {
+        Self { tokens, options, ..Default::default() }
+    }
+
+    /// Takes over the tokens of a lexer that has already run `lex()`, keeping
+    /// their spans so that errors and duplicate keys can point into the input.
+    pub fn from_lexer(lexer: JSONLexer, options: ParseOptions) -> Self {
+        Self { tokens: lexer.lexed_input, spans: lexer.spans, options, ..Default::default() }
+    }
+
+    /// Parses exactly one value; anything other than `Eof` after it is an error.
+    pub fn parse(&mut self) -> Result<JsonValue, JsonError> {
+        let value = self.parse_value()?;
+        match self.next() {
+            Token::Eof => Ok(value),
+            token => Err(self.error(format!("Unexpected token '{}' after the end of the document", token.extract_value()))),
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        self.tokens.get(self.pos).unwrap_or(&Token::Eof)
+    }
+
+    fn span_at(&self, i: usize) -> Span {
+        self.spans.get(i).copied().unwrap_or_default()
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        self.pos += 1;
+        token
+    }
+
+    // an error about the token just read
+    fn error(&self, message: impl Into<String>) -> JsonError {
+        JsonError::new(message, self.span_at(self.pos - 1))
+    }
+
+    pub fn parse_value(&mut self) -> Result<JsonValue, JsonError> {
+        match self.next() {
+            Token::OpenBrace(_) => self.nested(Self::parse_object),
+            Token::OpenBrack(_) => self.nested(Self::parse_array),
+            Token::StringLiteral(literal) => Ok(JsonValue::String(unescape_json_string(&literal).map_err(|err| self.error(err))?)),
+            Token::NumericLiteral(literal) => Ok(JsonValue::Number(JsonNumber::parse(&literal).map_err(|err| self.error(err))?)),
+            Token::BoolLiteral(literal) => Ok(JsonValue::Bool(literal == "true")),
+            Token::NullLiteral(_) => Ok(JsonValue::Null),
+            Token::Eof => Err(self.error("Unexpected end of input, expected a value")),
+            token => Err(self.error(format!("Unexpected token '{}', expected a value", token.extract_value()))),
+        }
+    }
+
+    // parses the contents of the container just opened, one level deeper
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<JsonValue, JsonError>) -> Result<JsonValue, JsonError> {
+        if self.depth == self.options.max_depth {
+            return Err(self.error(too_deep(self.options.max_depth)));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, JsonError> {
+        let mut object = if self.options.sorted_objects { JsonObject::sorted() } else { JsonObject::new() };
+        if let Token::CloseBrace(_) = self.peek() {
+            self.next();
+            return Ok(JsonValue::Object(object));
+        }
+        let mut key_spans: HashMap<String, Span> = HashMap::new();
+        loop {
+            let key_span = self.span_at(self.pos);
+            let key = match self.next() {
+                Token::StringLiteral(literal) => unescape_json_string(&literal).map_err(|err| self.error(err))?,
+                Token::Eof => return Err(self.error("Unexpected end of input, expected an object key")),
+                token => return Err(self.error(format!("Unexpected token '{}', expected an object key", token.extract_value()))),
+            };
+            match self.next() {
+                Token::Colon(_) => (),
+                token => return Err(self.error(format!("Expected ':' after object key, found '{}'", token.extract_value()))),
+            }
+            if let Some(&first) = key_spans.get(&key) {
+                self.duplicates.push(DuplicateKey { key: key.clone(), first, duplicate: key_span });
+                if self.options.duplicate_keys == DuplicateKeyPolicy::Error {
+                    return Err(JsonError::new(format!("Duplicate key {} found", escape_json_string(&key)), key_span));
+                }
+            } else {
+                key_spans.insert(key.clone(), key_span);
+            }
+            let value = self.parse_value()?;
+            object.insert_with_policy(key, value, self.options.duplicate_keys).map_err(|err| JsonError::new(err, key_span))?;
+            match self.next() {
+                Token::Comma(_) => continue,
+                Token::CloseBrace(_) => break,
+                Token::Eof => return Err(self.error("Unexpected end of input, expected ',' or '}'")),
+                token => return Err(self.error(format!("Expected ',' or '}}' in object, found '{}'", token.extract_value()))),
+            }
+        }
+        Ok(JsonValue::Object(object))
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, JsonError> {
+        let mut items = vec![];
+        if let Token::CloseBrack(_) = self.peek() {
+            self.next();
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            match self.next() {
+                Token::Comma(_) => continue,
+                Token::CloseBrack(_) => break,
+                Token::Eof => return Err(self.error("Unexpected end of input, expected ',' or ']'")),
+                token => return Err(self.error(format!("Expected ',' or ']' in array, found '{}'", token.extract_value()))),
+            }
+        }
+        Ok(JsonValue::Array(items))
+    }
+}
+
+pub fn parse_json(input: &str) -> Result<JsonValue, JsonError> {
+    parse_json_with(input, &ParseOptions::default())
+}
+
+pub fn parse_json_with(input: &str, options: &ParseOptions) -> Result<JsonValue, JsonError> {
+    let mut lexer = JSONLexer::from(String::from(input), IGNORE_WS);
+    lexer.lex()?;
+    let mut parser = JSONParser::from_lexer(lexer, options.clone());
+    parser.parse()
+}
+
+/// Lists every key that is repeated within a single object, in document order.
+pub fn find_duplicate_keys(input: &str) -> Result<Vec<DuplicateKey>, JsonError> {
+    let mut lexer = JSONLexer::from(String::from(input), IGNORE_WS);
+    lexer.lex()?;
+    let mut parser = JSONParser::from_lexer(lexer, ParseOptions::default());
+    parser.parse()?;
+    Ok(parser.duplicates)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::{find_duplicate_keys, parse_json, parse_json_with, DuplicateKey, JSONParser, ParseOptions};
+    use crate::error::JsonError;
+    use crate::number::JsonNumber;
+    use crate::object::DuplicateKeyPolicy;
+    use crate::position::Span;
+    use crate::value::JsonValue;
+    use crate::{minify_json, JSONLexer, IGNORE_WS};
+
+    #[test]
+    fn test_parse_scalars() {
+        assert_eq!(parse_json("null").unwrap(), JsonValue::Null);
+        assert_eq!(parse_json("true").unwrap(), JsonValue::Bool(true));
+        assert_eq!(parse_json(" -42 ").unwrap(), JsonValue::Number(JsonNumber::from(-42i64)));
+        assert_eq!(parse_json(r#""a\"b""#).unwrap(), JsonValue::String(String::from("a\"b")));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_json(r#"{"a":1,}"#).is_err());
+        assert!(parse_json(r#"{"a" 1}"#).is_err());
+        assert!(parse_json(r#"[1 2]"#).is_err());
+        assert!(parse_json(r#"[1,2"#).is_err());
+        assert!(parse_json(r#"{"a":1}{"b":2}"#).is_err());
+        assert!(parse_json(r#"{"a":"unterminated}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_error_spans() {
+        assert_eq!(parse_json("[1 2]").unwrap_err(), JsonError::new("Expected ',' or ']' in array, found '2'", Span::new(3, 4)));
+        assert_eq!(parse_json("{\"a\": \"\\x\"}").unwrap_err().span, Span::new(6, 10));
+        assert_eq!(parse_json("[1,").unwrap_err().span, Span::new(3, 3));
+        let error = parse_json("[True]").unwrap_err();
+        assert_eq!((error.span, error.help.as_deref()), (Span::new(1, 5), Some("JSON booleans are lowercase: did you mean `true`?")));
+    }
+
+    #[test]
+    fn test_parse_preserves_key_order() {
+        let input = fs::read_to_string("testfiles/long_test.json").expect("failed to read 'testfiles/long_test.json'");
+        let expected = fs::read_to_string("testfiles/long_test-min.json").expect("failed to read 'testfiles/long_test-min.json'");
+        let value = parse_json(&input).unwrap();
+        assert_eq!(value.to_json_string(), expected.trim_end());
+        assert_eq!(value.to_json_string(), minify_json(input).unwrap());
+    }
+
+    #[test]
+    fn test_parse_max_depth() {
+        let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert_eq!(parse_json(&deep).unwrap_err().to_string(), "Arrays and objects are nested more than 512 deep at position 512");
+        assert!(parse_json(&"[".repeat(100_000)).is_err());
+        assert!(parse_json(&format!("{}{}", "[".repeat(512), "]".repeat(512))).is_ok());
+        let shallow = ParseOptions { max_depth: 2, ..Default::default() };
+        assert!(parse_json_with(r#"{"a":[1]}"#, &shallow).is_ok());
+        assert!(parse_json_with(r#"{"a":[{}]}"#, &shallow).is_err());
+    }
+
+    #[test]
+    fn test_parse_sorted_objects() {
+        let options = ParseOptions { sorted_objects: true, ..Default::default() };
+        let value = parse_json_with(r#"{"b":1,"a":{"d":2,"c":3}}"#, &options).unwrap();
+        assert_eq!(value.to_json_string(), r#"{"a":{"c":3,"d":2},"b":1}"#);
+    }
+
+    #[test]
+    fn test_parse_duplicate_keys() {
+        let input = r#"{"a":1,"b":2,"a":3}"#;
+        assert_eq!(parse_json(input).unwrap().to_json_string(), r#"{"a":3,"b":2}"#);
+        let keep_first = ParseOptions { duplicate_keys: DuplicateKeyPolicy::KeepFirst, ..Default::default() };
+        assert_eq!(parse_json_with(input, &keep_first).unwrap().to_json_string(), r#"{"a":1,"b":2}"#);
+        let error = ParseOptions { duplicate_keys: DuplicateKeyPolicy::Error, ..Default::default() };
+        assert_eq!(parse_json_with(input, &error).unwrap_err(), JsonError::new("Duplicate key \"a\" found", Span::new(13, 16)));
+        let mut lexer = JSONLexer::from(String::from(input), IGNORE_WS);
+        lexer.lex().unwrap();
+        let mut parser = JSONParser::from_lexer(lexer, error);
+        assert!(parser.parse().is_err());
+        assert_eq!(parser.duplicates, vec![DuplicateKey { key: String::from("a"), first: Span::new(1, 4), duplicate: Span::new(13, 16) }]);
+        let warn = ParseOptions { duplicate_keys: DuplicateKeyPolicy::Warn, ..Default::default() };
+        assert_eq!(parse_json_with(input, &warn).unwrap().to_json_string(), r#"{"a":3,"b":2}"#);
+    }
+
+    #[test]
+    fn test_find_duplicate_keys() {
+        let input = "{\n  \"a\": {\"x\": 1, \"x\": 2},\n  \"b\": 2,\n  \"a\": 3\n}";
+        let duplicates = find_duplicate_keys(input).unwrap();
+        assert_eq!(duplicates, vec![
+            DuplicateKey { key: String::from("x"), first: Span::new(10, 13), duplicate: Span::new(18, 21) },
+            DuplicateKey { key: String::from("a"), first: Span::new(4, 7), duplicate: Span::new(39, 42) },
+        ]);
+        // the same key in sibling objects is fine
+        assert!(find_duplicate_keys(r#"[{"a":1},{"a":2}]"#).unwrap().is_empty());
+    }
+}
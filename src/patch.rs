@@ -0,0 +1,396 @@
+use crate::object::JsonObject;
+use crate::pointer::{parse_array_index, parse_pointer, push_pointer_token, resolve_pointer, resolve_pointer_mut};
+use crate::value::JsonValue;
+
+/// One operation of a JSON Patch document (RFC 6902). Paths are JSON Pointers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOp {
+    Add { path: String, value: JsonValue },
+    Remove { path: String },
+    Replace { path: String, value: JsonValue },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: JsonValue },
+}
+
+impl PatchOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatchOp::Add { .. } => "add",
+            PatchOp::Remove { .. } => "remove",
+            PatchOp::Replace { .. } => "replace",
+            PatchOp::Move { .. } => "move",
+            PatchOp::Copy { .. } => "copy",
+            PatchOp::Test { .. } => "test",
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOp::Add { path, .. } | PatchOp::Remove { path } | PatchOp::Replace { path, .. }
+            | PatchOp::Move { path, .. } | PatchOp::Copy { path, .. } | PatchOp::Test { path, .. } => path,
+        }
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mut obj = JsonObject::new();
+        obj.insert(String::from("op"), JsonValue::from(self.name()));
+        if let PatchOp::Move { from, .. } | PatchOp::Copy { from, .. } = self {
+            obj.insert(String::from("from"), JsonValue::from(from.as_str()));
+        }
+        obj.insert(String::from("path"), JsonValue::from(self.path()));
+        if let PatchOp::Add { value, .. } | PatchOp::Replace { value, .. } | PatchOp::Test { value, .. } = self {
+            obj.insert(String::from("value"), value.clone());
+        }
+        JsonValue::Object(obj)
+    }
+}
+
+/// Reads a patch document: an array of operation objects. Members other than
+/// the ones an operation needs are ignored, as the RFC requires.
+pub fn parse_patch(patch: &JsonValue) -> Result<Vec<PatchOp>, String> {
+    let items = match patch.as_array() {
+        Some(items) => items,
+        None => return Err(String::from("A JSON Patch must be an array of operations")),
+    };
+    items.iter().enumerate().map(|(i, item)| {
+        parse_patch_op(item).map_err(|err| format!("operation {}: {}", i, err))
+    }).collect()
+}
+
+fn parse_patch_op(item: &JsonValue) -> Result<PatchOp, String> {
+    let obj = match item.as_object() {
+        Some(obj) => obj,
+        None => return Err(format!("expected an object, found {}", item.kind())),
+    };
+    let string_member = |name: &str| match obj.get(name) {
+        Some(JsonValue::String(s)) => Ok(s.clone()),
+        Some(other) => Err(format!("\"{}\" must be a string, found {}", name, other.kind())),
+        None => Err(format!("missing \"{}\"", name)),
+    };
+    let value_member = || obj.get("value").cloned().ok_or_else(|| String::from("missing \"value\""));
+    let op = string_member("op")?;
+    let path = string_member("path")?;
+    match op.as_str() {
+        "add" => Ok(PatchOp::Add { path, value: value_member()? }),
+        "remove" => Ok(PatchOp::Remove { path }),
+        "replace" => Ok(PatchOp::Replace { path, value: value_member()? }),
+        "move" => Ok(PatchOp::Move { from: string_member("from")?, path }),
+        "copy" => Ok(PatchOp::Copy { from: string_member("from")?, path }),
+        "test" => Ok(PatchOp::Test { path, value: value_member()? }),
+        other => Err(format!("unknown op \"{}\"", other)),
+    }
+}
+
+pub fn patch_to_json(ops: &[PatchOp]) -> JsonValue {
+    JsonValue::Array(ops.iter().map(PatchOp::to_json).collect())
+}
+
+/// Applies a JSON Patch document to `doc`. Either every operation succeeds and
+/// the patched copy is returned, or the first failure is reported and nothing
+/// is changed.
+pub fn apply_patch(doc: &JsonValue, patch: &JsonValue) -> Result<JsonValue, String> {
+    apply_patch_ops(doc, &parse_patch(patch)?)
+}
+
+pub fn apply_patch_ops(doc: &JsonValue, ops: &[PatchOp]) -> Result<JsonValue, String> {
+    let mut patched = doc.clone();
+    for (i, op) in ops.iter().enumerate() {
+        apply_op(&mut patched, op).map_err(|err| format!("operation {} ({} {}): {}", i, op.name(), op.path(), err))?;
+    }
+    Ok(patched)
+}
+
+/// Applies one operation to `doc` in place. A `move` that fails to add may
+/// already have removed its source, so keep a copy if that matters.
+pub fn apply_op(doc: &mut JsonValue, op: &PatchOp) -> Result<(), String> {
+    match op {
+        PatchOp::Add { path, value } => add_at(doc, &parse_pointer(path)?, value.clone()),
+        PatchOp::Remove { path } => remove_at(doc, &parse_pointer(path)?).map(|_| ()),
+        PatchOp::Replace { path, value } => {
+            let target = resolve_pointer_mut(doc, &parse_pointer(path)?).ok_or("path does not exist")?;
+            *target = value.clone();
+            Ok(())
+        },
+        PatchOp::Move { from, path } => {
+            if path != from && path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(String::from("cannot move a value into one of its own children"));
+            }
+            let value = remove_at(doc, &parse_pointer(from)?).map_err(|err| format!("from: {}", err))?;
+            add_at(doc, &parse_pointer(path)?, value)
+        },
+        PatchOp::Copy { from, path } => {
+            let value = resolve_pointer(doc, &parse_pointer(from)?).ok_or("from: path does not exist")?.clone();
+            add_at(doc, &parse_pointer(path)?, value)
+        },
+        PatchOp::Test { path, value } => {
+            match resolve_pointer(doc, &parse_pointer(path)?) {
+                Some(actual) if actual == value => Ok(()),
+                Some(actual) => Err(format!("test failed, found {}", actual.to_json_string())),
+                None => Err(String::from("path does not exist")),
+            }
+        },
+    }
+}
+
+fn add_at(doc: &mut JsonValue, tokens: &[String], value: JsonValue) -> Result<(), String> {
+    let (last, parent_tokens) = match tokens.split_last() {
+        Some(split) => split,
+        None => {
+            *doc = value;
+            return Ok(());
+        },
+    };
+    match resolve_pointer_mut(doc, parent_tokens) {
+        Some(JsonValue::Object(obj)) => {
+            obj.insert(last.clone(), value);
+            Ok(())
+        },
+        Some(JsonValue::Array(arr)) => {
+            let i = if last == "-" { arr.len() } else { parse_array_index(last).ok_or("invalid array index")? };
+            if i > arr.len() {
+                return Err(format!("index {} is out of bounds for an array of length {}", i, arr.len()));
+            }
+            arr.insert(i, value);
+            Ok(())
+        },
+        Some(other) => Err(format!("cannot add a member to a {}", other.kind())),
+        None => Err(String::from("parent path does not exist")),
+    }
+}
+
+fn remove_at(doc: &mut JsonValue, tokens: &[String]) -> Result<JsonValue, String> {
+    let (last, parent_tokens) = match tokens.split_last() {
+        Some(split) => split,
+        None => return Err(String::from("cannot remove the whole document")),
+    };
+    match resolve_pointer_mut(doc, parent_tokens) {
+        Some(JsonValue::Object(obj)) => obj.remove(last).ok_or_else(|| String::from("path does not exist")),
+        Some(JsonValue::Array(arr)) => match parse_array_index(last) {
+            Some(i) if i < arr.len() => Ok(arr.remove(i)),
+            _ => Err(String::from("path does not exist")),
+        },
+        _ => Err(String::from("path does not exist")),
+    }
+}
+
+/// A patch that turns `a` into `b`. At every level the smaller of a nested
+/// patch and a plain `replace` is used, and array edits are aligned on the
+/// longest common subsequence so an insertion does not turn into a replace of
+/// every element after it.
+pub fn diff_to_patch(a: &JsonValue, b: &JsonValue) -> JsonValue {
+    let mut ops = vec![];
+    diff_into(a, b, "", &mut ops);
+    patch_to_json(&ops)
+}
+
+fn diff_into(a: &JsonValue, b: &JsonValue, path: &str, ops: &mut Vec<PatchOp>) {
+    if a == b {
+        return;
+    }
+    let mut nested = vec![];
+    match (a, b) {
+        (JsonValue::Object(a_obj), JsonValue::Object(b_obj)) => {
+            for (key, a_value) in a_obj.iter() {
+                let child = push_pointer_token(path, key);
+                match b_obj.get(key) {
+                    Some(b_value) => diff_into(a_value, b_value, &child, &mut nested),
+                    None => nested.push(PatchOp::Remove { path: child }),
+                }
+            }
+            for (key, b_value) in b_obj.iter() {
+                if !a_obj.contains_key(key) {
+                    nested.push(PatchOp::Add { path: push_pointer_token(path, key), value: b_value.clone() });
+                }
+            }
+        },
+        (JsonValue::Array(a_arr), JsonValue::Array(b_arr)) => diff_arrays(a_arr, b_arr, path, &mut nested),
+        _ => {
+            ops.push(PatchOp::Replace { path: String::from(path), value: b.clone() });
+            return;
+        },
+    }
+    let replace = PatchOp::Replace { path: String::from(path), value: b.clone() };
+    let nested_size: usize = nested.iter().map(|op| op.to_json().to_json_string().len()).sum();
+    if nested_size <= replace.to_json().to_json_string().len() {
+        ops.extend(nested);
+    } else {
+        ops.push(replace);
+    }
+}
+
+enum Edit {
+    Keep,
+    Delete(usize),
+    Insert(usize),
+}
+
+// past this many LCS cells the arrays are compared position by position instead
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+fn diff_arrays(a: &[JsonValue], b: &[JsonValue], path: &str, ops: &mut Vec<PatchOp>) {
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    let mut edits = vec![];
+    if a_mid.len() * b_mid.len() <= MAX_LCS_CELLS {
+        // lengths of the longest common subsequence of the suffixes a_mid[i..], b_mid[j..]
+        let width = b_mid.len() + 1;
+        let mut lcs = vec![0u32; (a_mid.len() + 1) * width];
+        for i in (0..a_mid.len()).rev() {
+            for j in (0..b_mid.len()).rev() {
+                lcs[i * width + j] = if a_mid[i] == b_mid[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a_mid.len() || j < b_mid.len() {
+            if i < a_mid.len() && j < b_mid.len() && a_mid[i] == b_mid[j] {
+                edits.push(Edit::Keep);
+                i += 1;
+                j += 1;
+            } else if j < b_mid.len() && (i == a_mid.len() || lcs[i * width + j + 1] >= lcs[(i + 1) * width + j]) {
+                edits.push(Edit::Insert(j));
+                j += 1;
+            } else {
+                edits.push(Edit::Delete(i));
+                i += 1;
+            }
+        }
+    } else {
+        edits.extend((0..a_mid.len()).map(Edit::Delete));
+        edits.extend((0..b_mid.len()).map(Edit::Insert));
+    }
+
+    // walk the edit script keeping track of where we are in the array as it is
+    // being patched; a run of deletes next to a run of inserts pairs up into changes
+    let mut index = prefix;
+    let mut k = 0;
+    while k < edits.len() {
+        if let Edit::Keep = edits[k] {
+            index += 1;
+            k += 1;
+            continue;
+        }
+        let run_end = edits[k..].iter().position(|e| matches!(e, Edit::Keep)).map_or(edits.len(), |p| k + p);
+        let deletes: Vec<usize> = edits[k..run_end].iter().filter_map(|e| match e { Edit::Delete(i) => Some(*i), _ => None }).collect();
+        let inserts: Vec<usize> = edits[k..run_end].iter().filter_map(|e| match e { Edit::Insert(j) => Some(*j), _ => None }).collect();
+        let paired = deletes.len().min(inserts.len());
+        for n in 0..paired {
+            diff_into(&a_mid[deletes[n]], &b_mid[inserts[n]], &push_pointer_token(path, &index.to_string()), ops);
+            index += 1;
+        }
+        for _ in paired..deletes.len() {
+            ops.push(PatchOp::Remove { path: push_pointer_token(path, &index.to_string()) });
+        }
+        for &j in inserts.iter().skip(paired) {
+            ops.push(PatchOp::Add { path: push_pointer_token(path, &index.to_string()), value: b_mid[j].clone() });
+            index += 1;
+        }
+        k = run_end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::{apply_patch, diff_to_patch};
+    use crate::parse_json;
+
+    fn check(doc: &str, patch: &str, expected: &str) {
+        let result = apply_patch(&parse_json(doc).unwrap(), &parse_json(patch).unwrap()).unwrap();
+        assert_eq!(result, parse_json(expected).unwrap());
+    }
+
+    fn check_err(doc: &str, patch: &str) -> String {
+        apply_patch(&parse_json(doc).unwrap(), &parse_json(patch).unwrap()).unwrap_err()
+    }
+
+    #[test]
+    fn test_apply_patch_rfc6902_examples() {
+        // Appendix A of RFC 6902
+        check(r#"{"foo":"bar"}"#, r#"[{"op":"add","path":"/baz","value":"qux"}]"#, r#"{"baz":"qux","foo":"bar"}"#);
+        check(r#"{"foo":["bar","baz"]}"#, r#"[{"op":"add","path":"/foo/1","value":"qux"}]"#, r#"{"foo":["bar","qux","baz"]}"#);
+        check(r#"{"baz":"qux","foo":"bar"}"#, r#"[{"op":"remove","path":"/baz"}]"#, r#"{"foo":"bar"}"#);
+        check(r#"{"foo":["bar","qux","baz"]}"#, r#"[{"op":"remove","path":"/foo/1"}]"#, r#"{"foo":["bar","baz"]}"#);
+        check(r#"{"baz":"qux","foo":"bar"}"#, r#"[{"op":"replace","path":"/baz","value":"boo"}]"#, r#"{"baz":"boo","foo":"bar"}"#);
+        check(r#"{"foo":{"bar":"baz","waldo":"fred"},"qux":{"corge":"grault"}}"#,
+            r#"[{"op":"move","from":"/foo/waldo","path":"/qux/thud"}]"#,
+            r#"{"foo":{"bar":"baz"},"qux":{"corge":"grault","thud":"fred"}}"#);
+        check(r#"{"foo":["all","grass","cows","eat"]}"#, r#"[{"op":"move","from":"/foo/1","path":"/foo/3"}]"#,
+            r#"{"foo":["all","cows","eat","grass"]}"#);
+        check(r#"{"baz":"qux","foo":["a",2,"c"]}"#,
+            r#"[{"op":"test","path":"/baz","value":"qux"},{"op":"test","path":"/foo/1","value":2}]"#,
+            r#"{"baz":"qux","foo":["a",2,"c"]}"#);
+        check_err(r#"{"baz":"qux"}"#, r#"[{"op":"test","path":"/baz","value":"bar"}]"#);
+        check(r#"{"foo":"bar"}"#, r#"[{"op":"add","path":"/child","value":{"grandchild":{}}}]"#,
+            r#"{"foo":"bar","child":{"grandchild":{}}}"#);
+        check(r#"{"foo":"bar"}"#, r#"[{"op":"add","path":"/baz","value":"qux","xyz":123}]"#, r#"{"foo":"bar","baz":"qux"}"#);
+        check_err(r#"{"foo":"bar"}"#, r#"[{"op":"add","path":"/baz/bat","value":"qux"}]"#);
+        check(r#"{"/":9,"~1":10}"#, r#"[{"op":"test","path":"/~01","value":10}]"#, r#"{"/":9,"~1":10}"#);
+        check_err(r#"{"/":9,"~1":10}"#, r#"[{"op":"test","path":"/~01","value":"10"}]"#);
+        check(r#"{"foo":["bar"]}"#, r#"[{"op":"add","path":"/foo/-","value":["abc","def"]}]"#, r#"{"foo":["bar",["abc","def"]]}"#);
+    }
+
+    #[test]
+    fn test_apply_patch_is_atomic() {
+        let doc = parse_json(r#"{"a":1,"b":[1,2]}"#).unwrap();
+        let patch = parse_json(r#"[{"op":"replace","path":"/a","value":2},{"op":"copy","from":"/b","path":"/c"},{"op":"remove","path":"/missing"}]"#).unwrap();
+        let err = apply_patch(&doc, &patch).unwrap_err();
+        assert_eq!(err, "operation 2 (remove /missing): path does not exist");
+        assert_eq!(doc, parse_json(r#"{"a":1,"b":[1,2]}"#).unwrap());
+        let moving_into_child = parse_json(r#"[{"op":"move","from":"/b","path":"/b/0"}]"#).unwrap();
+        assert!(apply_patch(&doc, &moving_into_child).is_err());
+        assert!(apply_patch(&doc, &parse_json(r#"[{"op":"frobnicate","path":"/a"}]"#).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_diff_to_patch_minimal() {
+        let a_list: Vec<String> = (1..=50).map(|n| n.to_string()).collect();
+        let b_list: Vec<String> = std::iter::once(0).chain((1..=50).filter(|n| *n != 10)).chain(std::iter::once(51))
+            .map(|n| n.to_string()).collect();
+        let a = parse_json(&format!(r#"{{"keep":1,"drop":2,"list":[{}],"deep":{{"x":[{{"y":1}}]}}}}"#, a_list.join(","))).unwrap();
+        let b = parse_json(&format!(r#"{{"keep":1,"list":[{}],"deep":{{"x":[{{"y":2}}]}},"new":true}}"#, b_list.join(","))).unwrap();
+        let patch = diff_to_patch(&a, &b);
+        assert_eq!(patch.to_json_string(), concat!(
+            r#"[{"op":"remove","path":"/drop"},"#,
+            r#"{"op":"add","path":"/list/0","value":0},"#,
+            r#"{"op":"remove","path":"/list/10"},"#,
+            r#"{"op":"add","path":"/list/50","value":51},"#,
+            r#"{"op":"replace","path":"/deep/x/0/y","value":2},"#,
+            r#"{"op":"add","path":"/new","value":true}]"#));
+        assert_eq!(apply_patch(&a, &patch).unwrap(), b);
+        assert_eq!(diff_to_patch(&a, &a).to_json_string(), "[]");
+        // a small array is cheaper to replace outright
+        let small = diff_to_patch(&parse_json("[1,2]").unwrap(), &parse_json("[0,1]").unwrap());
+        assert_eq!(small.to_json_string(), r#"[{"op":"replace","path":"","value":[0,1]}]"#);
+    }
+
+    #[test]
+    fn test_diff_to_patch_round_trips() {
+        let pairs = [
+            (r#"[1,2,3]"#, r#"[3,2,1]"#),
+            (r#"[1,2,3]"#, r#"[]"#),
+            (r#"[]"#, r#"[1,[2],{"3":4}]"#),
+            (r#"{"a":[1,{"b":2}]}"#, r#"{"a":[{"b":3},1]}"#),
+            (r#"{"a":1}"#, r#"[1]"#),
+        ];
+        for (a, b) in pairs {
+            let (a, b) = (parse_json(a).unwrap(), parse_json(b).unwrap());
+            assert_eq!(apply_patch(&a, &diff_to_patch(&a, &b)).unwrap(), b);
+        }
+        let original = parse_json(&fs::read_to_string("testfiles/noaa_climate_test.json").unwrap()).unwrap();
+        let mut edited = original.clone();
+        let data = edited.as_object_mut().unwrap().get_mut("data").unwrap().as_object_mut().unwrap();
+        data.remove("189512");
+        data.insert(String::from("202412"), parse_json(r#"{"value":"30.00","anomaly":"0.06"}"#).unwrap());
+        let patch = diff_to_patch(&original, &edited);
+        assert_eq!(patch.as_array().unwrap().len(), 2);
+        assert_eq!(apply_patch(&original, &patch).unwrap(), edited);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::value::JsonValue;
+
+/// Escapes one reference token of a JSON Pointer (RFC 6901): `~` becomes `~0`
+/// and `/` becomes `~1`.
+pub fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Appends `token` to `pointer` as a new reference token.
+pub fn push_pointer_token(pointer: &str, token: &str) -> String {
+    format!("{}/{}", pointer, escape_pointer_token(token))
+}
+
+/// Splits a JSON Pointer into its unescaped reference tokens. The empty pointer
+/// is the whole document and yields no tokens.
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    let rest = match pointer.strip_prefix('/') {
+        Some(rest) => rest,
+        None => return Err(format!("Invalid JSON Pointer '{}': must be empty or start with '/'", pointer)),
+    };
+    rest.split('/').map(|token| unescape_pointer_token(token)
+        .ok_or_else(|| format!("Invalid JSON Pointer '{}': '~' must be followed by '0' or '1'", pointer)))
+        .collect()
+}
+
+fn unescape_pointer_token(token: &str) -> Option<String> {
+    let mut out = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next() {
+                Some('0') => out.push('~'),
+                Some('1') => out.push('/'),
+                _ => return None,
+            },
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+/// Reads a reference token as an array index. Leading zeros and signs are not
+/// allowed, and `-` (the element after the last) is left to the caller.
+pub fn parse_array_index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// The value `tokens` lead to, if there is one.
+pub fn resolve_pointer<'a>(value: &'a JsonValue, tokens: &[String]) -> Option<&'a JsonValue> {
+    tokens.iter().try_fold(value, |current, token| match current {
+        JsonValue::Object(obj) => obj.get(token),
+        JsonValue::Array(arr) => parse_array_index(token).and_then(|i| arr.get(i)),
+        _ => None,
+    })
+}
+
+pub fn resolve_pointer_mut<'a>(value: &'a mut JsonValue, tokens: &[String]) -> Option<&'a mut JsonValue> {
+    tokens.iter().try_fold(value, |current, token| match current {
+        JsonValue::Object(obj) => obj.get_mut(token),
+        JsonValue::Array(arr) => parse_array_index(token).and_then(move |i| arr.get_mut(i)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_pointer_token, parse_array_index, parse_pointer, push_pointer_token, resolve_pointer};
+    use crate::parse_json;
+
+    #[test]
+    fn test_escape_pointer_token() {
+        assert_eq!(escape_pointer_token("a/b~c"), "a~1b~0c");
+        assert_eq!(escape_pointer_token("~1"), "~01");
+        assert_eq!(push_pointer_token("", "web-app"), "/web-app");
+        assert_eq!(push_pointer_token("/web-app", "0"), "/web-app/0");
+    }
+
+    #[test]
+    fn test_parse_pointer() {
+        assert_eq!(parse_pointer("").unwrap(), Vec::<String>::new());
+        assert_eq!(parse_pointer("/").unwrap(), vec![""]);
+        assert_eq!(parse_pointer("/a~1b/~01/0").unwrap(), vec!["a/b", "~1", "0"]);
+        assert!(parse_pointer("a").is_err());
+        assert!(parse_pointer("/a~2").is_err());
+        assert_eq!(parse_array_index("10"), Some(10));
+        assert_eq!(parse_array_index("01"), None);
+        assert_eq!(parse_array_index("-"), None);
+    }
+
+    #[test]
+    fn test_resolve_pointer() {
+        let value = parse_json(r#"{"a":[{"b/c":1}],"":2}"#).unwrap();
+        let at = |p: &str| resolve_pointer(&value, &parse_pointer(p).unwrap()).map(|v| v.to_json_string());
+        assert_eq!(at("/a/0/b~1c"), Some(String::from("1")));
+        assert_eq!(at("/"), Some(String::from("2")));
+        assert_eq!(at("/a/1"), None);
+        assert_eq!(at("/a/00"), None);
+        assert_eq!(at(""), Some(value.to_json_string()));
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::cst::{Cst, CstNode};
+use crate::pointer::push_pointer_token;
+
+/// Byte range of a token in the lexer input, `start` inclusive and `end` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span { start: self.start.min(other.start), end: self.end.max(other.end) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// A 1-based line and column. Columns count chars, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineCol {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for LineCol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// Start offsets of every line in a text, for turning byte offsets into `LineCol`s.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        Self { text, line_starts }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Line and column of a byte offset. Offsets past the end map to the end of the text.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.line_starts[line];
+        let col = self.text.get(line_start..offset)
+            .map(|prefix| prefix.chars().count())
+            .unwrap_or(offset - line_start);
+        LineCol { line: line + 1, col: col + 1 }
+    }
+
+    /// Byte offset of a line and column, the inverse of `line_col`. The column
+    /// may be one past the end of the line; anything further is `None`.
+    pub fn offset(&self, at: LineCol) -> Option<usize> {
+        let line = self.line_span(at.line)?;
+        let col = at.col.checked_sub(1)?;
+        let text = &self.text[line.start..line.end];
+        text.char_indices().map(|(i, _)| i).chain([text.len()]).nth(col).map(|i| line.start + i)
+    }
+
+    /// Byte range of 1-based `line`, without its line break.
+    pub fn line_span(&self, line: usize) -> Option<Span> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self.line_starts.get(line).map_or(self.text.len(), |next| next - 1);
+        let end = if self.text[start..end].ends_with('\r') { end - 1 } else { end };
+        Some(Span { start, end })
+    }
+
+    /// Zero-based line and UTF-16 column of a byte offset, the way the Language
+    /// Server Protocol counts positions.
+    pub fn utf16_position(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.line_starts[line];
+        let character = self.text.get(line_start..offset)
+            .map(|prefix| prefix.encode_utf16().count())
+            .unwrap_or(offset - line_start);
+        (line, character)
+    }
+
+    /// Byte offset of a zero-based line and UTF-16 column. Columns past the end
+    /// of the line stop at its end, and lines past the last at the end of the text.
+    pub fn utf16_offset(&self, line: usize, character: usize) -> usize {
+        let Some(span) = self.line_span(line + 1) else { return self.text.len() };
+        let mut units = 0;
+        for (i, c) in self.text[span.start..span.end].char_indices() {
+            if units >= character {
+                return span.start + i;
+            }
+            units += c.len_utf16();
+        }
+        span.end
+    }
+}
+
+/// A value in a `PositionIndex`, with the JSON Pointer that selects it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub pointer: String,
+    /// The key of an object member; `None` for the root and array elements.
+    pub key: Option<Span>,
+    pub value: Span,
+}
+
+impl Location {
+    /// From the key, if any, to the end of the value.
+    pub fn span(&self) -> Span {
+        self.key.map_or(self.value, |key| key.to(self.value))
+    }
+}
+
+/// Where every value of a document is, for going from a place in the text to
+/// the JSON Pointer of what is written there and back.
+#[derive(Debug, Clone)]
+pub struct PositionIndex<'a> {
+    lines: LineIndex<'a>,
+    /// Every value in document order, so a container comes before its contents.
+    locations: Vec<Location>,
+    /// The index of the container each location is in.
+    parents: Vec<Option<usize>>,
+    by_pointer: HashMap<String, usize>,
+}
+
+impl<'a> PositionIndex<'a> {
+    pub fn new(input: &'a str) -> Result<Self, String> {
+        let cst = Cst::parse(input)?;
+        let mut index = PositionIndex { lines: LineIndex::new(input), locations: vec![], parents: vec![], by_pointer: HashMap::new() };
+        index.add_node(cst.root(), String::new(), None, None);
+        Ok(index)
+    }
+
+    pub fn lines(&self) -> &LineIndex<'a> {
+        &self.lines
+    }
+
+    /// The innermost value whose key or value covers `offset`. Whitespace and
+    /// punctuation between members belong to the container around them.
+    pub fn at_offset(&self, offset: usize) -> Option<&Location> {
+        let after = self.locations.partition_point(|location| location.span().start <= offset);
+        let mut i = after.checked_sub(1)?;
+        loop {
+            let span = self.locations[i].span();
+            if offset < span.end {
+                return Some(&self.locations[i]);
+            }
+            i = self.parents[i]?;
+        }
+    }
+
+    /// Like `at_offset`, for a 1-based line and column.
+    pub fn at_line_col(&self, at: LineCol) -> Option<&Location> {
+        self.at_offset(self.lines.offset(at)?)
+    }
+
+    /// The value `pointer` selects. With duplicate keys that is the last one,
+    /// as when parsing; the members it overwrites aren't indexed.
+    pub fn locate(&self, pointer: &str) -> Option<&Location> {
+        self.by_pointer.get(pointer).map(|&i| &self.locations[i])
+    }
+
+    fn add_node(&mut self, node: &CstNode, pointer: String, key: Option<Span>, parent: Option<usize>) {
+        let this = self.locations.len();
+        self.by_pointer.insert(pointer.clone(), this);
+        self.locations.push(Location { pointer: pointer.clone(), key, value: node.span() });
+        self.parents.push(parent);
+        let CstNode::Container { items, .. } = node else { return };
+        let keys: Vec<Option<String>> = items.iter().map(|item| item.key_text()).collect();
+        let last: HashMap<&str, usize> = keys.iter().enumerate()
+            .filter_map(|(i, key)| Some((key.as_deref()?, i)))
+            .collect();
+        for (i, item) in items.iter().enumerate() {
+            let child = match &keys[i] {
+                // a member overwritten by a later one with the same key isn't
+                // in the parsed value, so neither it nor its contents get a pointer
+                Some(name) if last[name.as_str()] != i => continue,
+                Some(name) => push_pointer_token(&pointer, name),
+                None => format!("{}/{}", pointer, i),
+            };
+            let key = item.key.as_ref().map(|(key, _)| key.span);
+            self.add_node(&item.value, child, key, Some(this));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineCol, LineIndex, PositionIndex, Span};
+
+    #[test]
+    fn test_line_col() {
+        let text = "{\n  \"a\": 1,\n  \"é\": 2\n}";
+        let index = LineIndex::new(text);
+        assert_eq!(index.line_col(0), LineCol { line: 1, col: 1 });
+        assert_eq!(index.line_col(4), LineCol { line: 2, col: 3 });
+        let after_e = text.find("\": 2").unwrap();
+        assert_eq!(index.line_col(after_e), LineCol { line: 3, col: 5 });
+        assert_eq!(index.line_col(text.len()), LineCol { line: 4, col: 2 });
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_span(2).map(|span| &text[span.start..span.end]), Some("  \"a\": 1,"));
+        assert_eq!(index.line_span(4).map(|span| &text[span.start..span.end]), Some("}"));
+        assert_eq!(index.line_span(5), None);
+        assert_eq!(LineIndex::new("a\r\nb").line_span(1), Some(Span::new(0, 1)));
+    }
+
+    #[test]
+    fn test_utf16_positions() {
+        let text = "[\n\"\u{1f600}\", \"é\"\n]";
+        let index = LineIndex::new(text);
+        let after_emoji = text.find("\", ").unwrap();
+        assert_eq!(index.utf16_position(after_emoji), (1, 3));
+        assert_eq!(index.utf16_offset(1, 3), after_emoji);
+        assert_eq!(index.utf16_position(text.len()), (2, 1));
+        assert_eq!(index.utf16_offset(1, 100), text.rfind('\n').unwrap());
+        assert_eq!(index.utf16_offset(9, 0), text.len());
+        assert_eq!(LineIndex::new("[1,\r\n2]").utf16_offset(0, 100), 3);
+    }
+
+    #[test]
+    fn test_offset() {
+        let text = "ab\r\né\n";
+        let index = LineIndex::new(text);
+        assert_eq!(index.offset(LineCol { line: 1, col: 3 }), Some(2));
+        assert_eq!(index.offset(LineCol { line: 2, col: 2 }), Some(6));
+        assert_eq!(index.offset(LineCol { line: 2, col: 3 }), None);
+        assert_eq!(index.offset(LineCol { line: 3, col: 1 }), Some(7));
+        assert_eq!(index.offset(LineCol { line: 4, col: 1 }), None);
+        for offset in [0, 1, 4, 6] {
+            assert_eq!(index.offset(index.line_col(offset)), Some(offset));
+        }
+    }
+
+    #[test]
+    fn test_position_index() {
+        let text = "{\n  \"a/b\": [1, {\"c\": null}],\n  \"d\": \"x\"\n}";
+        let index = PositionIndex::new(text).unwrap();
+        let pointer_at = |line, col| index.at_line_col(LineCol { line, col }).map(|location| location.pointer.as_str());
+        assert_eq!(pointer_at(2, 3), Some("/a~1b"));
+        assert_eq!(pointer_at(2, 11), Some("/a~1b/0"));
+        assert_eq!(pointer_at(2, 13), Some("/a~1b"));
+        assert_eq!(pointer_at(2, 21), Some("/a~1b/1/c"));
+        assert_eq!(pointer_at(3, 1), Some(""));
+        assert_eq!(pointer_at(9, 1), None);
+
+        let c = index.locate("/a~1b/1/c").unwrap();
+        assert_eq!(&text[c.span().start..c.span().end], "\"c\": null");
+        assert_eq!(index.lines().line_col(c.value.start), LineCol { line: 2, col: 20 });
+        assert_eq!(index.locate("").unwrap().value, Span::new(0, text.len()));
+        assert!(index.locate("/a~1b/2").is_none());
+        assert!(PositionIndex::new("[1,]").is_err());
+
+        let text = "{\"a\":{\"x\":1},\"a\":2}";
+        let index = PositionIndex::new(text).unwrap();
+        assert!(index.locate("/a/x").is_none());
+        assert_eq!(index.locate("/a").unwrap().value, Span::new(17, 18));
+        assert_eq!(index.at_offset(7).unwrap().pointer, "");
+    }
+}
@@ -0,0 +1,648 @@
+use std::collections::HashSet;
+
+use crate::error::JsonError;
+use crate::hints::{number_hint, quote_hint, word_hint};
+use crate::number::{is_json_number, JsonNumber};
+use crate::object::JsonObject;
+use crate::parser::{too_deep, MAX_DEPTH};
+use crate::position::Span;
+use crate::value::{unescape_json_string, JsonValue};
+use crate::{literal_name, StringScanner, Token};
+
+/// A token of possibly broken JSON. Besides the real tokens this covers the
+/// usual mistakes, so that the parser can say what is wrong and carry on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lexeme {
+    OpenBrace,
+    CloseBrace,
+    OpenBrack,
+    CloseBrack,
+    Colon,
+    Comma,
+    /// A double-quoted string as written, quotes included. An unterminated one
+    /// runs to the end of its line and has no closing quote.
+    String(String),
+    /// A string in quotes JSON doesn't allow: `'...'` or typographic quotes.
+    OtherQuoted { text: String, quote: char },
+    /// Anything that starts like a number, valid or not.
+    Number(String),
+    True,
+    False,
+    Null,
+    /// A bare word that isn't a JSON literal: `True`, `None`, `NaN`, an unquoted key...
+    Word(String),
+    /// A `//` or `/* */` comment.
+    Comment,
+    Unknown(char),
+    Eof,
+}
+
+impl Lexeme {
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Lexeme::OpenBrace => String::from("'{'"),
+            Lexeme::CloseBrace => String::from("'}'"),
+            Lexeme::OpenBrack => String::from("'['"),
+            Lexeme::CloseBrack => String::from("']'"),
+            Lexeme::Colon => String::from("':'"),
+            Lexeme::Comma => String::from("','"),
+            Lexeme::String(_) | Lexeme::OtherQuoted { .. } => String::from("a string"),
+            Lexeme::Number(raw) | Lexeme::Word(raw) => format!("'{}'", raw),
+            Lexeme::True => String::from("'true'"),
+            Lexeme::False => String::from("'false'"),
+            Lexeme::Null => String::from("'null'"),
+            Lexeme::Comment => String::from("a comment"),
+            Lexeme::Unknown(c) => format!("'{}'", c),
+            Lexeme::Eof => String::from("end of input"),
+        }
+    }
+
+    pub(crate) fn starts_value(&self) -> bool {
+        !matches!(self, Lexeme::CloseBrace | Lexeme::CloseBrack | Lexeme::Colon | Lexeme::Comma | Lexeme::Eof)
+    }
+}
+
+fn closing_quote(open: char) -> char {
+    match open {
+        '\u{201c}' => '\u{201d}',
+        '\u{2018}' => '\u{2019}',
+        c => c,
+    }
+}
+
+/// Splits `input` into lexemes without ever failing. The last one is `Eof`.
+pub fn lex_tolerant(input: &str) -> Vec<(Lexeme, Span)> {
+    let bytes = input.as_bytes();
+    let mut lexemes = vec![];
+    let mut pos = 0;
+    let end_of_line = |from: usize| input[from..].find('\n').map_or(input.len(), |i| from + i);
+    while let Some(c) = input[pos..].chars().next() {
+        let start = pos;
+        let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+        let lexeme = match c {
+            ' ' | '\t' | '\n' | '\r' => {
+                pos += 1;
+                continue;
+            },
+            '{' => { pos += 1; Lexeme::OpenBrace },
+            '}' => { pos += 1; Lexeme::CloseBrace },
+            '[' => { pos += 1; Lexeme::OpenBrack },
+            ']' => { pos += 1; Lexeme::CloseBrack },
+            ':' => { pos += 1; Lexeme::Colon },
+            ',' => { pos += 1; Lexeme::Comma },
+            '"' => {
+                let mut scanner = StringScanner::default();
+                let line_end = end_of_line(pos);
+                pos = bytes[pos + 1..line_end].iter().position(|&byte| scanner.is_closing(byte))
+                    .map_or(line_end, |close| pos + 1 + close + 1);
+                Lexeme::String(String::from(&input[start..pos]))
+            },
+            '\'' | '\u{201c}' | '\u{201d}' | '\u{2018}' | '\u{2019}' => {
+                let close = closing_quote(c);
+                let body_start = pos + c.len_utf8();
+                let line_end = end_of_line(pos);
+                let mut escaped = false;
+                let body_end = input[body_start..line_end].char_indices()
+                    .find(|&(_, c)| {
+                        let found = c == close && !escaped;
+                        escaped = c == '\\' && !escaped;
+                        found
+                    })
+                    .map_or(line_end, |(i, _)| body_start + i);
+                pos = if body_end < line_end { body_end + close.len_utf8() } else { line_end };
+                Lexeme::OtherQuoted { text: String::from(&input[body_start..body_end]), quote: c }
+            },
+            '-' if input[pos + 1..].starts_with(|c: char| c.is_alphabetic()) => {
+                pos += 1;
+                pos += input[pos..].find(|c: char| !is_word(c)).unwrap_or(input.len() - pos);
+                Lexeme::Word(String::from(&input[start..pos]))
+            },
+            '-' | '.' | '0'..='9' => {
+                pos += 1;
+                pos += input[pos..].find(|c: char| !(is_word(c) || matches!(c, '.' | '+' | '-'))).unwrap_or(input.len() - pos);
+                Lexeme::Number(String::from(&input[start..pos]))
+            },
+            '/' if input[pos..].starts_with("//") => {
+                pos = end_of_line(pos);
+                Lexeme::Comment
+            },
+            '/' if input[pos..].starts_with("/*") => {
+                pos = input[pos + 2..].find("*/").map_or(input.len(), |i| pos + 2 + i + 2);
+                Lexeme::Comment
+            },
+            c if is_word(c) => {
+                pos += input[pos..].find(|c: char| !is_word(c)).unwrap_or(input.len() - pos);
+                let word = &input[start..pos];
+                match literal_name(word) {
+                    Some(Token::BoolLiteral(_)) if word == "true" => Lexeme::True,
+                    Some(Token::BoolLiteral(_)) => Lexeme::False,
+                    Some(_) => Lexeme::Null,
+                    None => Lexeme::Word(String::from(word)),
+                }
+            },
+            c => {
+                pos += c.len_utf8();
+                Lexeme::Unknown(c)
+            },
+        };
+        lexemes.push((lexeme, Span::new(start, pos)));
+    }
+    lexemes.push((Lexeme::Eof, Span::new(input.len(), input.len())));
+    lexemes
+}
+
+/// The result of parsing with recovery: as much of the document as could be
+/// made sense of, and every problem found on the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recovered {
+    pub value: Option<JsonValue>,
+    pub errors: Vec<JsonError>,
+}
+
+/// Parses `input` without stopping at the first error. After a problem the
+/// parser picks up again at the next comma or closing bracket, so one pass
+/// reports everything; members and elements it could not read are left out
+/// of the partial tree.
+pub fn parse_json_recovering(input: &str) -> Recovered {
+    let (cursor, skipped) = LexemeCursor::new(input);
+    let mut parser = RecoveringParser { cursor, errors: vec![], error_starts: HashSet::new() };
+    for (lexeme, span) in skipped {
+        match lexeme {
+            Lexeme::Comment => parser.error(JsonError::new("Comments are not allowed in JSON", span)
+                .with_help("remove the comment, or keep the note in a member such as \"_comment\"")),
+            Lexeme::Unknown(c) => parser.error(JsonError::new(format!("Unexpected character '{}'", c), span)),
+            _ => (),
+        }
+    }
+    let value = parser.parse_document();
+    let mut errors = parser.errors;
+    errors.sort_by_key(|err| err.span.start);
+    Recovered { value, errors }
+}
+
+/// A place in the lexemes of broken input, for the parsers that work through
+/// it: the recovering parser here and the repairer.
+pub(crate) struct LexemeCursor {
+    lexemes: Vec<(Lexeme, Span)>,
+    pos: usize,
+    /// The closing brackets of the containers we are inside, innermost last.
+    pub(crate) closers: Vec<Lexeme>,
+    /// Where the last consumed lexeme ended.
+    last_end: usize,
+}
+
+impl LexemeCursor {
+    /// The lexemes of `input` to parse, and apart from them the comments and
+    /// unknown characters, which never belong in the tree.
+    pub(crate) fn new(input: &str) -> (Self, Vec<(Lexeme, Span)>) {
+        let (skipped, lexemes) = lex_tolerant(input).into_iter()
+            .partition(|(lexeme, _)| matches!(lexeme, Lexeme::Comment | Lexeme::Unknown(_)));
+        (LexemeCursor { lexemes, pos: 0, closers: vec![], last_end: 0 }, skipped)
+    }
+
+    /// How many lexemes have been consumed, to check that a loop moves on.
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub(crate) fn peek(&self) -> &Lexeme {
+        &self.lexemes[self.pos.min(self.lexemes.len() - 1)].0
+    }
+
+    pub(crate) fn peek_span(&self) -> Span {
+        self.lexemes[self.pos.min(self.lexemes.len() - 1)].1
+    }
+
+    pub(crate) fn peek_second(&self) -> &Lexeme {
+        &self.lexemes[(self.pos + 1).min(self.lexemes.len() - 1)].0
+    }
+
+    pub(crate) fn advance(&mut self) -> (Lexeme, Span) {
+        let (lexeme, span) = self.lexemes[self.pos.min(self.lexemes.len() - 1)].clone();
+        if lexeme != Lexeme::Eof {
+            self.pos += 1;
+            self.last_end = span.end;
+        }
+        (lexeme, span)
+    }
+
+    /// The empty span just after the last consumed lexeme.
+    pub(crate) fn here(&self) -> Span {
+        Span::new(self.last_end, self.last_end)
+    }
+
+    /// Whether opening another container would nest deeper than `MAX_DEPTH`.
+    pub(crate) fn too_deep(&self) -> bool {
+        self.closers.len() >= MAX_DEPTH
+    }
+
+    /// Consumes the rest of a container whose opening bracket was just read,
+    /// without looking inside, up to its closing bracket or the end of input.
+    pub(crate) fn skip_container(&mut self) {
+        let mut depth = 1usize;
+        while depth > 0 {
+            match self.advance().0 {
+                Lexeme::OpenBrace | Lexeme::OpenBrack => depth += 1,
+                Lexeme::CloseBrace | Lexeme::CloseBrack => depth -= 1,
+                Lexeme::Eof => return,
+                _ => (),
+            }
+        }
+    }
+
+    /// Whether `closer` belongs to a container further out than the innermost.
+    pub(crate) fn closes_outer(&self, closer: &Lexeme) -> bool {
+        self.closers[..self.closers.len().saturating_sub(1)].contains(closer)
+    }
+}
+
+struct RecoveringParser {
+    cursor: LexemeCursor,
+    errors: Vec<JsonError>,
+    error_starts: HashSet<usize>,
+}
+
+impl RecoveringParser {
+    // one error per position is enough; the first says the most
+    fn error(&mut self, error: JsonError) {
+        if self.error_starts.insert(error.span.start) {
+            self.errors.push(error);
+        }
+    }
+
+    fn parse_document(&mut self) -> Option<JsonValue> {
+        if *self.cursor.peek() == Lexeme::Eof {
+            let span = self.cursor.peek_span();
+            self.error(JsonError::new("Unexpected end of input, expected a value", span));
+            return None;
+        }
+        let value = self.parse_value();
+        if *self.cursor.peek() != Lexeme::Eof {
+            let span = self.cursor.peek_span();
+            let message = format!("Unexpected {} after the end of the document", self.cursor.peek().describe());
+            self.error(JsonError::new(message, span).with_help("a JSON document holds one value; use `json-mini seq` for several"));
+        }
+        value
+    }
+
+    fn parse_value(&mut self) -> Option<JsonValue> {
+        let span = self.cursor.peek_span();
+        match self.cursor.peek().clone() {
+            Lexeme::OpenBrace | Lexeme::OpenBrack if self.cursor.too_deep() => {
+                self.cursor.advance();
+                self.cursor.skip_container();
+                self.error(JsonError::new(too_deep(MAX_DEPTH), span));
+                None
+            },
+            Lexeme::OpenBrace => {
+                self.cursor.advance();
+                Some(self.parse_object(span))
+            },
+            Lexeme::OpenBrack => {
+                self.cursor.advance();
+                Some(self.parse_array(span))
+            },
+            _ => self.parse_scalar(span),
+        }
+    }
+
+    // kept apart from `parse_value` so that the frames of the recursion stay small
+    fn parse_scalar(&mut self, span: Span) -> Option<JsonValue> {
+        match self.cursor.peek().clone() {
+            Lexeme::String(raw) => {
+                self.cursor.advance();
+                Some(JsonValue::String(self.string_content(&raw, span)))
+            },
+            Lexeme::OtherQuoted { text, quote } => {
+                self.cursor.advance();
+                self.error(JsonError::new("Strings must use double quotes", span).with_help(quote_hint(quote, &text)));
+                Some(JsonValue::String(text))
+            },
+            Lexeme::Number(raw) => {
+                self.cursor.advance();
+                if is_json_number(&raw) {
+                    JsonNumber::parse(&raw).ok().map(JsonValue::Number)
+                } else {
+                    let mut error = JsonError::new(format!("Invalid number '{}'", raw), span);
+                    error.help = number_hint(&raw);
+                    self.error(error);
+                    None
+                }
+            },
+            Lexeme::True => { self.cursor.advance(); Some(JsonValue::Bool(true)) },
+            Lexeme::False => { self.cursor.advance(); Some(JsonValue::Bool(false)) },
+            Lexeme::Null => { self.cursor.advance(); Some(JsonValue::Null) },
+            Lexeme::Word(word) => {
+                self.cursor.advance();
+                let help = word_hint(&word).unwrap_or_else(|| format!("if this is text, quote it: \"{}\"", word));
+                self.error(JsonError::new(format!("'{}' is not a JSON value", word), span).with_help(help));
+                None
+            },
+            lexeme => {
+                let message = format!("Expected a value, found {}", lexeme.describe());
+                self.error(JsonError::new(message, span));
+                None
+            },
+        }
+    }
+
+    // the text of a string lexeme, reporting a missing quote or bad escapes
+    fn string_content(&mut self, raw: &str, span: Span) -> String {
+        let mut literal = String::from(raw);
+        if !is_terminated(&literal) {
+            self.error(JsonError::new("Unterminated string", span)
+                .with_help("add the closing '\"'; a string cannot span lines, so write a line break as \\n"));
+            literal.push('"');
+        }
+        match unescape_json_string(&literal) {
+            Ok(text) => text,
+            Err(err) => {
+                self.error(JsonError::new(err, span));
+                String::from(&literal[1..literal.len() - 1])
+            },
+        }
+    }
+
+    // Handles a closing bracket of the wrong kind. If it closes a container
+    // further out, this one is unclosed and gives way; otherwise it is stray
+    // and skipped. Gives true if the current container should end.
+    fn wrong_closer(&mut self, open_span: Span, open: char) -> bool {
+        let span = self.cursor.peek_span();
+        let closer = self.cursor.peek().clone();
+        if self.cursor.closes_outer(&closer) {
+            self.error(unclosed(open, open_span));
+            return true;
+        }
+        self.error(JsonError::new(format!("Unexpected {}", closer.describe()), span));
+        self.cursor.advance();
+        false
+    }
+
+    // skips to the next comma or closing bracket at this level
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.cursor.peek() {
+                Lexeme::Eof => return,
+                Lexeme::Comma | Lexeme::CloseBrace | Lexeme::CloseBrack if depth == 0 => return,
+                Lexeme::OpenBrace | Lexeme::OpenBrack => depth += 1,
+                Lexeme::CloseBrace | Lexeme::CloseBrack => depth -= 1,
+                _ => (),
+            }
+            self.cursor.advance();
+        }
+    }
+
+    fn parse_object(&mut self, open_span: Span) -> JsonValue {
+        self.cursor.closers.push(Lexeme::CloseBrace);
+        let mut obj = JsonObject::new();
+        let mut trailing_comma: Option<Span> = None;
+        loop {
+            let span = self.cursor.peek_span();
+            match self.cursor.peek() {
+                Lexeme::CloseBrace => {
+                    if let Some(comma) = trailing_comma {
+                        self.error(trailing_comma_error('}', comma));
+                    }
+                    self.cursor.advance();
+                    break;
+                },
+                Lexeme::Eof => {
+                    self.error(unclosed('{', open_span));
+                    break;
+                },
+                Lexeme::CloseBrack => {
+                    if self.wrong_closer(open_span, '{') {
+                        break;
+                    }
+                    continue;
+                },
+                _ => (),
+            }
+            trailing_comma = None;
+            let key = match self.cursor.peek().clone() {
+                Lexeme::String(raw) => Some(self.string_content(&raw, span)),
+                Lexeme::OtherQuoted { text, quote } => {
+                    self.error(JsonError::new("Strings must use double quotes", span).with_help(quote_hint(quote, &text)));
+                    Some(text)
+                },
+                Lexeme::Word(word) | Lexeme::Number(word) => {
+                    self.error(unquoted_key(&word, span));
+                    Some(word)
+                },
+                lexeme @ (Lexeme::True | Lexeme::False | Lexeme::Null) => {
+                    let word = lexeme.describe().trim_matches('\'').to_string();
+                    self.error(unquoted_key(&word, span));
+                    Some(word)
+                },
+                lexeme => {
+                    self.error(JsonError::new(format!("Expected an object key, found {}", lexeme.describe()), span));
+                    None
+                },
+            };
+            let Some(key) = key else {
+                if *self.cursor.peek() == Lexeme::Comma {
+                    self.cursor.advance();
+                } else {
+                    self.synchronize();
+                    if *self.cursor.peek() == Lexeme::Comma {
+                        trailing_comma = Some(self.cursor.advance().1);
+                    }
+                }
+                continue;
+            };
+            self.cursor.advance();
+            if *self.cursor.peek() == Lexeme::Colon {
+                self.cursor.advance();
+            } else {
+                let here = self.cursor.here();
+                self.error(JsonError::new("Expected ':' after object key", here));
+            }
+            if self.cursor.peek().starts_value() {
+                let before = self.cursor.pos();
+                if let Some(value) = self.parse_value() {
+                    obj.insert(key, value);
+                }
+                if self.cursor.pos() == before {
+                    self.cursor.advance();
+                }
+            } else {
+                let span = self.cursor.peek_span();
+                let message = format!("Expected a value, found {}", self.cursor.peek().describe());
+                self.error(JsonError::new(message, span));
+            }
+            match self.cursor.peek() {
+                Lexeme::Comma => trailing_comma = Some(self.cursor.advance().1),
+                Lexeme::CloseBrace | Lexeme::CloseBrack | Lexeme::Eof => (),
+                Lexeme::String(_) | Lexeme::OtherQuoted { .. } | Lexeme::Word(_) if *self.cursor.peek_second() == Lexeme::Colon => {
+                    let here = self.cursor.here();
+                    self.error(JsonError::new("Missing ',' between object members", here).with_help("add a ',' after the previous member"));
+                },
+                lexeme => {
+                    let span = self.cursor.peek_span();
+                    let message = format!("Expected ',' or '}}' in object, found {}", lexeme.describe());
+                    self.error(JsonError::new(message, span));
+                    self.synchronize();
+                    if *self.cursor.peek() == Lexeme::Comma {
+                        trailing_comma = Some(self.cursor.advance().1);
+                    }
+                },
+            }
+        }
+        self.cursor.closers.pop();
+        JsonValue::Object(obj)
+    }
+
+    fn parse_array(&mut self, open_span: Span) -> JsonValue {
+        self.cursor.closers.push(Lexeme::CloseBrack);
+        let mut arr = vec![];
+        let mut trailing_comma: Option<Span> = None;
+        loop {
+            let span = self.cursor.peek_span();
+            match self.cursor.peek() {
+                Lexeme::CloseBrack => {
+                    if let Some(comma) = trailing_comma {
+                        self.error(trailing_comma_error(']', comma));
+                    }
+                    self.cursor.advance();
+                    break;
+                },
+                Lexeme::Eof => {
+                    self.error(unclosed('[', open_span));
+                    break;
+                },
+                Lexeme::CloseBrace => {
+                    if self.wrong_closer(open_span, '[') {
+                        break;
+                    }
+                    continue;
+                },
+                Lexeme::Comma => {
+                    self.error(JsonError::new("Expected a value, found ','", span));
+                    trailing_comma = Some(self.cursor.advance().1);
+                    continue;
+                },
+                _ => (),
+            }
+            trailing_comma = None;
+            let before = self.cursor.pos();
+            if let Some(value) = self.parse_value() {
+                arr.push(value);
+            }
+            if self.cursor.pos() == before {
+                self.cursor.advance();
+            }
+            match self.cursor.peek() {
+                Lexeme::Comma => trailing_comma = Some(self.cursor.advance().1),
+                Lexeme::CloseBrack | Lexeme::CloseBrace | Lexeme::Eof => (),
+                lexeme if lexeme.starts_value() => {
+                    let here = self.cursor.here();
+                    self.error(JsonError::new("Missing ',' between array elements", here).with_help("add a ',' after the previous element"));
+                },
+                lexeme => {
+                    let span = self.cursor.peek_span();
+                    let message = format!("Expected ',' or ']' in array, found {}", lexeme.describe());
+                    self.error(JsonError::new(message, span));
+                    self.synchronize();
+                    if *self.cursor.peek() == Lexeme::Comma {
+                        trailing_comma = Some(self.cursor.advance().1);
+                    }
+                },
+            }
+        }
+        self.cursor.closers.pop();
+        JsonValue::Array(arr)
+    }
+}
+
+fn unclosed(open: char, span: Span) -> JsonError {
+    let close = if open == '{' { '}' } else { ']' };
+    JsonError::new(format!("Unclosed '{}'", open), span).with_help(format!("add a matching '{}'", close))
+}
+
+fn trailing_comma_error(close: char, span: Span) -> JsonError {
+    JsonError::new(format!("Trailing comma before '{}'", close), span)
+        .with_help("JSON does not allow a comma after the last item; remove it")
+}
+
+fn unquoted_key(key: &str, span: Span) -> JsonError {
+    JsonError::new("Object keys must be strings", span).with_help(format!("put the key in double quotes: \"{}\"", key))
+}
+
+// whether a `String` lexeme has its closing quote: the literal must end in a
+// quote with an even run of backslashes before it
+pub(crate) fn is_terminated(literal: &str) -> bool {
+    if literal.len() < 2 || !literal.ends_with('"') {
+        return false;
+    }
+    let body = &literal[..literal.len() - 1];
+    body.bytes().rev().take_while(|&b| b == b'\\').count() % 2 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::parse_json_recovering;
+    use crate::parse_json;
+
+    fn messages(input: &str) -> Vec<(usize, String)> {
+        parse_json_recovering(input).errors.into_iter().map(|err| (err.span.start, err.message)).collect()
+    }
+
+    #[test]
+    fn test_valid_input_has_no_errors() {
+        for name in ["test", "long_test", "noaa_climate_test", "massive_arr_test"] {
+            let input = fs::read_to_string(format!("testfiles/{}.json", name)).unwrap();
+            let recovered = parse_json_recovering(&input);
+            assert!(recovered.errors.is_empty(), "{}: {:?}", name, recovered.errors);
+            assert_eq!(recovered.value, Some(parse_json(&input).unwrap()));
+        }
+        assert_eq!(parse_json_recovering(r#""a\"b\\""#).value.unwrap().as_str(), Some("a\"b\\"));
+    }
+
+    #[test]
+    fn test_reports_every_error() {
+        let input = "{\"a\": 1,, \"b\": tru, \"c\": [1 2,], 'd': \"x\" \"e\": 01}";
+        assert_eq!(messages(input), [
+            (8, String::from("Expected an object key, found ','")),
+            (15, String::from("'tru' is not a JSON value")),
+            (27, String::from("Missing ',' between array elements")),
+            (29, String::from("Trailing comma before ']'")),
+            (33, String::from("Strings must use double quotes")),
+            (41, String::from("Missing ',' between object members")),
+            (47, String::from("Invalid number '01'")),
+        ]);
+        let partial = parse_json_recovering(input).value.unwrap();
+        assert_eq!(partial.to_json_string(), r#"{"a":1,"c":[1,2],"d":"x"}"#);
+    }
+
+    #[test]
+    fn test_recovers_from_brackets_and_strings() {
+        assert_eq!(messages("[1, {\"a\": 2]"), [(4, String::from("Unclosed '{'"))]);
+        assert_eq!(messages("{\"a\": [1, 2}"), [(6, String::from("Unclosed '['"))]);
+        assert_eq!(messages("[1]]"), [(3, String::from("Unexpected ']' after the end of the document"))]);
+        assert_eq!(messages("{\"a\": \"open\n}"), [(6, String::from("Unterminated string"))]);
+        let recovered = parse_json_recovering("// note\n[1, /* two */ 2 #\n]");
+        assert_eq!(recovered.errors.iter().map(|err| err.span.start).collect::<Vec<_>>(), [0, 12, 24]);
+        assert_eq!(recovered.value.unwrap().to_json_string(), "[1,2]");
+        assert_eq!(messages(""), [(0, String::from("Unexpected end of input, expected a value"))]);
+        let deep = format!("{}1{}, x]", "[".repeat(100_000), "]".repeat(99_999));
+        let errors = messages(&deep);
+        assert_eq!(errors[0], (512, String::from("Arrays and objects are nested more than 512 deep")));
+        assert_eq!(errors[1].0, deep.len() - 2);
+    }
+
+    #[test]
+    fn test_hints() {
+        let input = "{\n  name: 'x',\n  \"ok\": True,\n  \"n\": NaN,\n  \"z\": 007,\n}";
+        let helps: Vec<String> = parse_json_recovering(input).errors.into_iter().filter_map(|err| err.help).collect();
+        assert_eq!(helps, [
+            "put the key in double quotes: \"name\"",
+            "JSON strings use double quotes: \"x\"",
+            "JSON booleans are lowercase: did you mean `true`?",
+            "JSON numbers cannot be NaN or Infinity; use null or a string such as \"NaN\"",
+            "numbers cannot have leading zeros: did you mean `7`?",
+            "JSON does not allow a comma after the last item; remove it",
+        ]);
+    }
+}
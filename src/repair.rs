@@ -0,0 +1,351 @@
+use crate::hints::foreign_literal;
+use crate::number::is_json_number;
+use crate::parser::{parse_json, too_deep, MAX_DEPTH};
+use crate::position::Span;
+use crate::recover::{is_terminated, Lexeme, LexemeCursor};
+use crate::value::escape_json_string;
+
+/// One change made while repairing, located in the original input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub span: Span,
+    pub description: String,
+}
+
+/// Valid JSON made from broken input, and the fixes it took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repaired {
+    pub json: String,
+    pub fixes: Vec<Fix>,
+}
+
+/// Turns almost-JSON into JSON: quotes bare keys and words, converts single and
+/// typographic quotes, inserts missing commas and colons, drops trailing commas
+/// and comments, replaces Python and JavaScript literals, and closes whatever is
+/// still open at the end. Everything the repair does not touch is copied as is,
+/// so the output keeps the input's layout.
+///
+/// Fails if the input has no value in it, or could not be made valid.
+pub fn repair_json(input: &str) -> Result<Repaired, String> {
+    let (cursor, skipped) = LexemeCursor::new(input);
+    let mut repairer = Repairer { cursor, edits: vec![], too_deep: false };
+    for (lexeme, span) in skipped {
+        match lexeme {
+            Lexeme::Comment => repairer.edit(span, "", "removed comment"),
+            Lexeme::Unknown(c) => repairer.edit(span, "", format!("removed unexpected character '{}'", c)),
+            _ => (),
+        }
+    }
+    repairer.skip_until_value();
+    if *repairer.cursor.peek() == Lexeme::Eof {
+        return Err(String::from("Nothing to repair: the input has no JSON value in it"));
+    }
+    repairer.value();
+    if repairer.too_deep {
+        return Err(format!("Could not repair the input: {}", too_deep(MAX_DEPTH)));
+    }
+    while *repairer.cursor.peek() != Lexeme::Eof {
+        let (lexeme, span) = repairer.cursor.advance();
+        repairer.edit(span, "", format!("removed {} after the end of the document", lexeme.describe()));
+    }
+
+    let mut edits = repairer.edits;
+    // insertions go before a removal starting at the same place
+    edits.sort_by_key(|(fix, _)| (fix.span.start, fix.span.end));
+    let mut json = String::with_capacity(input.len());
+    let mut copied = 0;
+    for (fix, replacement) in &edits {
+        json.push_str(&input[copied..fix.span.start]);
+        json.push_str(replacement);
+        copied = fix.span.end;
+    }
+    json.push_str(&input[copied..]);
+    parse_json(&json).map_err(|err| format!("Could not repair the input: {}", err))?;
+    Ok(Repaired { json, fixes: edits.into_iter().map(|(fix, _)| fix).collect() })
+}
+
+struct Repairer {
+    cursor: LexemeCursor,
+    edits: Vec<(Fix, String)>,
+    /// Set when a container was skipped for being nested too deeply.
+    too_deep: bool,
+}
+
+impl Repairer {
+    fn edit(&mut self, span: Span, replacement: &str, description: impl Into<String>) {
+        self.edits.push((Fix { span, description: description.into() }, String::from(replacement)));
+    }
+
+    fn insert(&mut self, text: &str, description: impl Into<String>) {
+        self.edit(self.cursor.here(), text, description);
+    }
+
+    fn skip_until_value(&mut self) {
+        while !self.cursor.peek().starts_value() && *self.cursor.peek() != Lexeme::Eof {
+            let (lexeme, span) = self.cursor.advance();
+            self.edit(span, "", format!("removed unexpected {}", lexeme.describe()));
+        }
+    }
+
+    // the caller has checked that the next lexeme starts a value
+    fn value(&mut self) {
+        let (lexeme, span) = self.cursor.advance();
+        match lexeme {
+            Lexeme::OpenBrace | Lexeme::OpenBrack if self.cursor.too_deep() => {
+                self.cursor.skip_container();
+                self.too_deep = true;
+            },
+            Lexeme::OpenBrace => self.container(Lexeme::CloseBrace),
+            Lexeme::OpenBrack => self.container(Lexeme::CloseBrack),
+            Lexeme::String(raw) => self.string(&raw, span),
+            Lexeme::OtherQuoted { text, quote } => self.other_quoted(&text, quote, span),
+            Lexeme::Number(raw) if !is_json_number(&raw) => match repair_number(&raw) {
+                Some(fixed) => self.edit(span, &fixed, format!("rewrote number '{}' as {}", raw, fixed)),
+                None => self.edit(span, "null", format!("replaced invalid number '{}' with null", raw)),
+            },
+            Lexeme::Word(word) => match foreign_literal(&word) {
+                Some(literal) => self.edit(span, literal, format!("replaced '{}' with {}", word, literal)),
+                None => self.edit(span, &escape_json_string(&word), format!("quoted bare word '{}'", word)),
+            },
+            _ => (),
+        }
+    }
+
+    fn string(&mut self, raw: &str, span: Span) {
+        if !is_terminated(raw) {
+            let body = escape_string_body(raw[1..].trim_end(), '"');
+            self.edit(span, &format!("\"{}\"", body), "closed unterminated string");
+            return;
+        }
+        let body = &raw[1..raw.len() - 1];
+        let fixed = escape_string_body(body, '"');
+        if fixed != body {
+            self.edit(span, &format!("\"{}\"", fixed), "escaped control characters and stray backslashes in string");
+        }
+    }
+
+    fn other_quoted(&mut self, text: &str, quote: char, span: Span) {
+        let json = format!("\"{}\"", escape_string_body(text, quote));
+        let description = if quote == '\'' { "replaced single quotes with double quotes" } else { "replaced typographic quotes with double quotes" };
+        self.edit(span, &json, description);
+    }
+
+    // an object member name, written however the input wrote it
+    fn key(&mut self) {
+        match self.cursor.peek().clone() {
+            Lexeme::OpenBrace | Lexeme::OpenBrack => {
+                self.insert(" \"\":", "inserted missing key");
+                return;
+            },
+            _ => (),
+        }
+        let (lexeme, span) = self.cursor.advance();
+        match lexeme {
+            Lexeme::String(raw) => self.string(&raw, span),
+            Lexeme::OtherQuoted { text, quote } => self.other_quoted(&text, quote, span),
+            Lexeme::Word(word) | Lexeme::Number(word) => {
+                self.edit(span, &escape_json_string(&word), format!("quoted key '{}'", word));
+            },
+            literal => {
+                let word = literal.describe().trim_matches('\'').to_string();
+                self.edit(span, &escape_json_string(&word), format!("quoted key '{}'", word));
+            },
+        }
+    }
+
+    // the members or elements of a container whose opening bracket was just read
+    fn container(&mut self, closer: Lexeme) {
+        let is_object = closer == Lexeme::CloseBrace;
+        let close = if is_object { "}" } else { "]" };
+        self.cursor.closers.push(closer.clone());
+        let mut expect_item = true;
+        let mut trailing_comma = None;
+        loop {
+            let (lexeme, span) = (self.cursor.peek().clone(), self.cursor.peek_span());
+            match lexeme {
+                _ if lexeme == closer => {
+                    self.cursor.advance();
+                    self.drop_trailing_comma(trailing_comma);
+                    break;
+                },
+                Lexeme::CloseBrace | Lexeme::CloseBrack => {
+                    // closes an outer container: this one was left open
+                    if self.cursor.closes_outer(&lexeme) {
+                        self.drop_trailing_comma(trailing_comma);
+                        self.insert(close, format!("inserted missing '{}'", close));
+                        break;
+                    }
+                    self.cursor.advance();
+                    self.edit(span, "", format!("removed unmatched {}", lexeme.describe()));
+                },
+                Lexeme::Eof => {
+                    self.drop_trailing_comma(trailing_comma);
+                    self.insert(close, format!("inserted missing '{}' at the end of the input", close));
+                    break;
+                },
+                Lexeme::Comma => {
+                    self.cursor.advance();
+                    if expect_item {
+                        self.edit(span, "", "removed extra comma");
+                    } else {
+                        expect_item = true;
+                        trailing_comma = Some(span);
+                    }
+                },
+                Lexeme::Colon => {
+                    self.cursor.advance();
+                    self.edit(span, "", "removed unexpected ':'");
+                },
+                _ => {
+                    if !expect_item {
+                        self.insert(",", "inserted missing comma");
+                    }
+                    expect_item = false;
+                    trailing_comma = None;
+                    if is_object {
+                        self.member();
+                    } else {
+                        self.value();
+                    }
+                },
+            }
+        }
+        self.cursor.closers.pop();
+    }
+
+    fn drop_trailing_comma(&mut self, comma: Option<Span>) {
+        if let Some(comma) = comma {
+            self.edit(comma, "", "removed trailing comma");
+        }
+    }
+
+    fn member(&mut self) {
+        self.key();
+        if *self.cursor.peek() == Lexeme::Colon {
+            self.cursor.advance();
+        } else if self.cursor.peek().starts_value() {
+            self.insert(":", "inserted missing ':'");
+        } else {
+            self.insert(": null", "inserted missing value");
+            return;
+        }
+        if self.cursor.peek().starts_value() {
+            self.value();
+        } else {
+            self.insert(" null", "inserted missing value");
+        }
+    }
+}
+
+// leading zeros, a bare decimal point, or a hexadecimal literal
+fn repair_number(raw: &str) -> Option<String> {
+    let (sign, unsigned) = match raw.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", raw),
+    };
+    // without a digit there is no number to keep, only a sign or a point
+    if !unsigned.bytes().any(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if let Some(hex) = unsigned.strip_prefix("0x").or_else(|| unsigned.strip_prefix("0X")) {
+        return u64::from_str_radix(hex, 16).ok().map(|n| format!("{}{}", sign, n));
+    }
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => unsigned.split_at(i),
+        None => (unsigned, ""),
+    };
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (mantissa, None),
+    };
+    let int = match int.trim_start_matches('0') {
+        "" => "0",
+        trimmed => trimmed,
+    };
+    let fixed = match frac {
+        Some("") => format!("{}{}.0{}", sign, int, exponent),
+        Some(frac) => format!("{}{}.{}{}", sign, int, frac, exponent),
+        None => format!("{}{}{}", sign, int, exponent),
+    };
+    if is_json_number(&fixed) { Some(fixed) } else { None }
+}
+
+// string content from a string quoted with `quote`, ready to go between double
+// quotes: raw control characters and unknown escapes are escaped, and quotes
+// swap roles
+fn escape_string_body(body: &str, quote: char) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek().copied() {
+                Some(next) if next == quote && quote != '"' => {
+                    chars.next();
+                    out.push(next);
+                },
+                Some('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => {
+                    out.push('\\');
+                    out.extend(chars.next());
+                },
+                Some('u') if chars.clone().skip(1).take(4).filter(char::is_ascii_hexdigit).count() == 4 => {
+                    out.push('\\');
+                },
+                _ => out.push_str("\\\\"),
+            },
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::repair_json;
+
+    fn repaired(input: &str) -> String {
+        repair_json(input).unwrap().json
+    }
+
+    #[test]
+    fn test_repair_common_mistakes() {
+        assert_eq!(repaired("{name: 'Ann', 'age': 30,}"), "{\"name\": \"Ann\", \"age\": 30}");
+        assert_eq!(repaired("[1 2\n 3]"), "[1, 2,\n 3]");
+        assert_eq!(repaired("{\"a\": True, \"b\": None, \"c\": NaN}"), "{\"a\": true, \"b\": null, \"c\": null}");
+        assert_eq!(repaired("{\u{201c}a\u{201d}: \u{2018}it's\u{2019}}"), "{\"a\": \"it's\"}");
+        assert_eq!(repaired("{\"a\": [1, {\"b\": 2"), "{\"a\": [1, {\"b\": 2}]}");
+        assert_eq!(repaired("[1, // one\n 2, /* two */]"), "[1, \n 2 ]");
+        assert_eq!(repaired("{\"a\" 1 \"b\": 007, \"c\": .5}"), "{\"a\": 1, \"b\": 7, \"c\": 0.5}");
+        assert_eq!(repaired("['say \"hi\"', 'it\\'s']"), "[\"say \\\"hi\\\"\", \"it's\"]");
+    }
+
+    #[test]
+    fn test_repair_structure() {
+        assert_eq!(repaired("[1, {\"a\": 2]"), "[1, {\"a\": 2}]");
+        assert_eq!(repaired("{\"a\": [1, 2}"), "{\"a\": [1, 2]}");
+        assert_eq!(repaired("[1,,2]]"), "[1,2]");
+        assert_eq!(repaired("{\"a\": , \"b\"}"), "{\"a\": null , \"b\": null}");
+        assert_eq!(repaired("{\"a\": \"unterminated\n}"), "{\"a\": \"unterminated\"\n}");
+        assert_eq!(repaired("```\n{\"a\": 1}\n```"), "\n{\"a\": 1}\n");
+        assert_eq!(repaired("[-, -.]"), "[null, null]");
+        assert!(repair_json("   ").is_err());
+        assert!(repair_json(&"[".repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn test_repair_reports_fixes() {
+        let input = "{a: 1,}";
+        let repaired = repair_json(input).unwrap();
+        let fixes: Vec<(usize, &str)> = repaired.fixes.iter().map(|fix| (fix.span.start, fix.description.as_str())).collect();
+        assert_eq!(fixes, [(1, "quoted key 'a'"), (5, "removed trailing comma")]);
+
+        let valid = "{\n  \"a\": [1, 2.5e3, \"\\u00e9\"]\n}";
+        let untouched = repair_json(valid).unwrap();
+        assert_eq!(untouched.json, valid);
+        assert!(untouched.fixes.is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::error::JsonError;
+use crate::parser::{JSONParser, ParseOptions};
+use crate::position::Span;
+use crate::value::JsonValue;
+use crate::{JSONLexer, Token, IGNORE_WS};
+
+/// The record separator that starts each text of an RFC 7464 sequence.
+pub const RECORD_SEPARATOR: char = '\u{1e}';
+
+/// How the documents of a multi-document input are delimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceFormat {
+    /// Values one after another, with optional whitespace between them.
+    Concatenated,
+    /// An RFC 7464 JSON text sequence: each text follows a `0x1E` byte.
+    RecordSeparated,
+}
+
+impl SequenceFormat {
+    /// Record separated if the input contains a `0x1E` anywhere.
+    pub fn detect(input: &str) -> Self {
+        if input.contains(RECORD_SEPARATOR) { SequenceFormat::RecordSeparated } else { SequenceFormat::Concatenated }
+    }
+}
+
+/// One top-level value of a sequence and where its text is in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub span: Span,
+    pub value: JsonValue,
+}
+
+/// Parses every top-level value of `input`.
+pub fn parse_sequence(input: &str, format: SequenceFormat) -> Result<Vec<Document>, JsonError> {
+    match format {
+        SequenceFormat::Concatenated => parse_concatenated(input, 0),
+        SequenceFormat::RecordSeparated => parse_record_separated(input),
+    }
+}
+
+fn parse_concatenated(input: &str, offset: usize) -> Result<Vec<Document>, JsonError> {
+    let mut lexer = JSONLexer::from(String::from(input), IGNORE_WS);
+    // lexer errors count from the start of the record; make them count from
+    // the start of the whole input
+    lexer.lex().map_err(|err| JsonError { span: shift(err.span, offset), ..err })?;
+    let tokens = lexer.lexed_input;
+    let spans: Vec<Span> = lexer.spans.iter().map(|&span| shift(span, offset)).collect();
+
+    let mut documents = vec![];
+    let mut i = 0;
+    while tokens[i] != Token::Eof {
+        let start = i;
+        let mut depth = 0usize;
+        loop {
+            match &tokens[i] {
+                Token::OpenBrace(_) | Token::OpenBrack(_) => depth += 1,
+                Token::CloseBrace(_) | Token::CloseBrack(_) if depth > 0 => depth -= 1,
+                Token::Eof => {
+                    let err = JsonError::new("Unexpected end of input", spans[i]);
+                    return Err(prefixed(&format!("document {}", documents.len() + 1), err));
+                },
+                _ => (),
+            }
+            i += 1;
+            if depth == 0 {
+                break;
+            }
+        }
+        let mut parser = JSONParser::from(tokens[start..i].to_vec(), ParseOptions::default());
+        parser.spans = spans[start..i].to_vec();
+        let value = parser.parse().map_err(|err| prefixed(&format!("document {}", documents.len() + 1), err))?;
+        documents.push(Document { span: spans[start].to(spans[i - 1]), value });
+    }
+    Ok(documents)
+}
+
+fn shift(span: Span, offset: usize) -> Span {
+    Span::new(span.start + offset, span.end + offset)
+}
+
+// says which document or record an error is in
+fn prefixed(prefix: &str, err: JsonError) -> JsonError {
+    JsonError { message: format!("{}: {}", prefix, err.message), ..err }
+}
+
+// Each record is RS, one JSON text, LF. Empty records are skipped, but anything
+// before the first RS other than whitespace is an error.
+fn parse_record_separated(input: &str) -> Result<Vec<Document>, JsonError> {
+    let mut documents = vec![];
+    let mut offset = 0;
+    for (i, record) in input.split(RECORD_SEPARATOR).enumerate() {
+        let span = Span::new(offset, offset + record.len());
+        if i == 0 && !record.trim().is_empty() {
+            return Err(JsonError::new("Text before the first record separator", span));
+        }
+        if i > 0 && !record.trim().is_empty() {
+            let mut parsed = parse_concatenated(record, offset)
+                .map_err(|err| prefixed(&format!("record {}", i), err))?;
+            if parsed.len() != 1 {
+                return Err(JsonError::new(format!("record {}: expected one value, found {}", i, parsed.len()), span));
+            }
+            documents.push(parsed.remove(0));
+        }
+        offset += record.len() + RECORD_SEPARATOR.len_utf8();
+    }
+    Ok(documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_sequence, SequenceFormat};
+    use crate::position::Span;
+
+    #[test]
+    fn test_parse_concatenated() {
+        let input = "{\"a\":1}{\"b\":2}\n[3] \"x\" 4 null";
+        let docs = parse_sequence(input, SequenceFormat::detect(input)).unwrap();
+        let texts: Vec<&str> = docs.iter().map(|doc| &input[doc.span.start..doc.span.end]).collect();
+        assert_eq!(texts, ["{\"a\":1}", "{\"b\":2}", "[3]", "\"x\"", "4", "null"]);
+        assert_eq!(docs[1].value.to_json_string(), "{\"b\":2}");
+        assert!(parse_sequence("", SequenceFormat::Concatenated).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_concatenated_errors() {
+        let err = parse_sequence("{\"a\":1} {\"b\" 2}", SequenceFormat::Concatenated).unwrap_err();
+        assert_eq!(err.to_string(), "document 2: Expected ':' after object key, found '2' at position 13");
+        assert!(parse_sequence("[1] [2", SequenceFormat::Concatenated).unwrap_err().message.starts_with("document 2:"));
+        assert!(parse_sequence("[1]]", SequenceFormat::Concatenated).is_err());
+    }
+
+    #[test]
+    fn test_parse_record_separated() {
+        let input = "\u{1e}{\"a\":1}\n\u{1e}\u{1e} [2]\n";
+        assert_eq!(SequenceFormat::detect(input), SequenceFormat::RecordSeparated);
+        let docs = parse_sequence(input, SequenceFormat::RecordSeparated).unwrap();
+        assert_eq!(docs.iter().map(|doc| doc.span).collect::<Vec<_>>(), [Span::new(1, 8), Span::new(12, 15)]);
+        assert!(parse_sequence("\u{1e}1 2\n", SequenceFormat::RecordSeparated).unwrap_err().message.starts_with("record 1:"));
+        assert!(parse_sequence("0\u{1e}1\n", SequenceFormat::RecordSeparated).is_err());
+        // positions count from the start of the input, not of the record
+        let err = parse_sequence("\u{1e}1\n\u{1e}[01]\n", SequenceFormat::RecordSeparated).unwrap_err();
+        assert_eq!(err.span.start, 5);
+    }
+}
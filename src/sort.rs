@@ -0,0 +1,220 @@
+use std::cmp::Ordering;
+
+use crate::parser::{too_deep, MAX_DEPTH};
+use crate::value::{unescape_json_string, JsonValue};
+use crate::Token;
+
+/// How object keys are ordered when sorting is requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyOrder {
+    /// By Unicode code point.
+    #[default]
+    Lexicographic,
+    /// Runs of digits compare by numeric value, so `"9" < "10"` and `"189512" < "1895120"`.
+    Natural,
+    /// Ignoring case, with code point order as the tie-breaker so the result is stable.
+    CaseInsensitive,
+}
+
+impl KeyOrder {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "lexicographic" | "lex" => Ok(KeyOrder::Lexicographic),
+            "natural" => Ok(KeyOrder::Natural),
+            "case-insensitive" | "ci" => Ok(KeyOrder::CaseInsensitive),
+            _ => Err(format!("Unknown key order '{}', expected one of lexicographic, natural, case-insensitive", name)),
+        }
+    }
+}
+
+pub fn compare_keys(a: &str, b: &str, order: KeyOrder) -> Ordering {
+    match order {
+        KeyOrder::Lexicographic => a.cmp(b),
+        KeyOrder::Natural => compare_natural(a, b).then_with(|| a.cmp(b)),
+        KeyOrder::CaseInsensitive => a.chars().flat_map(char::to_lowercase)
+            .cmp(b.chars().flat_map(char::to_lowercase))
+            .then_with(|| a.cmp(b)),
+    }
+}
+
+fn compare_natural(a: &str, b: &str) -> Ordering {
+    let mut a_rest = a;
+    let mut b_rest = b;
+    loop {
+        let (a_chunk, a_tail) = split_chunk(a_rest);
+        let (b_chunk, b_tail) = split_chunk(b_rest);
+        if a_chunk.is_empty() || b_chunk.is_empty() {
+            return a_chunk.len().cmp(&b_chunk.len());
+        }
+        let a_digits = a_chunk.starts_with(|c: char| c.is_ascii_digit());
+        let b_digits = b_chunk.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = match (a_digits, b_digits) {
+            (true, true) => {
+                let a_num = a_chunk.trim_start_matches('0');
+                let b_num = b_chunk.trim_start_matches('0');
+                a_num.len().cmp(&b_num.len())
+                    .then_with(|| a_num.cmp(b_num))
+                    .then_with(|| a_chunk.len().cmp(&b_chunk.len()))
+            },
+            _ => a_chunk.cmp(b_chunk),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        a_rest = a_tail;
+        b_rest = b_tail;
+    }
+}
+
+// a maximal run of digits, or a maximal run of anything else
+fn split_chunk(s: &str) -> (&str, &str) {
+    let digits = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s.find(|c: char| c.is_ascii_digit() != digits).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Sorts the keys of every object in `value`. Only objects nested less than
+/// `max_depth` containers deep are touched (the root is at depth 0); `None`
+/// sorts all of them.
+pub fn sort_value_keys(value: &mut JsonValue, order: KeyOrder, max_depth: Option<usize>) {
+    sort_value_keys_at(value, order, max_depth, 0);
+}
+
+fn sort_value_keys_at(value: &mut JsonValue, order: KeyOrder, max_depth: Option<usize>, depth: usize) {
+    if max_depth.is_some_and(|max| depth >= max) {
+        return;
+    }
+    match value {
+        JsonValue::Object(obj) => {
+            obj.sort_by(|a, b| compare_keys(a, b, order));
+            for (_, child) in obj.iter_mut() {
+                sort_value_keys_at(child, order, max_depth, depth + 1);
+            }
+        },
+        JsonValue::Array(arr) => {
+            for child in arr.iter_mut() {
+                sort_value_keys_at(child, order, max_depth, depth + 1);
+            }
+        },
+        _ => (),
+    }
+}
+
+/// Same as `sort_value_keys`, but reorders a lexed token stream (whitespace
+/// already dropped) so that every literal keeps its exact source text.
+pub fn sort_token_keys(tokens: Vec<Token>, order: KeyOrder, max_depth: Option<usize>) -> Result<Vec<Token>, String> {
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut iter = tokens.into_iter().peekable();
+    copy_sorted_value(&mut iter, &mut out, order, max_depth, 0)?;
+    out.extend(iter);
+    Ok(out)
+}
+
+type TokenIter = std::iter::Peekable<std::vec::IntoIter<Token>>;
+
+fn copy_sorted_value(tokens: &mut TokenIter, out: &mut Vec<Token>, order: KeyOrder, max_depth: Option<usize>, depth: usize) -> Result<(), String> {
+    let token = match tokens.next() {
+        Some(token) => token,
+        None => return Err(String::from("Unexpected end of input, expected a value")),
+    };
+    if depth == MAX_DEPTH && matches!(token, Token::OpenBrace(_) | Token::OpenBrack(_)) {
+        return Err(too_deep(MAX_DEPTH));
+    }
+    match token {
+        Token::OpenBrace(_) => {
+            out.push(token);
+            let mut members: Vec<(String, Vec<Token>)> = vec![];
+            if let Some(Token::CloseBrace(_)) = tokens.peek() {
+                out.extend(tokens.next());
+                return Ok(());
+            }
+            loop {
+                let mut member = vec![];
+                let key = match tokens.next() {
+                    Some(Token::StringLiteral(literal)) => {
+                        let key = unescape_json_string(&literal)?;
+                        member.push(Token::StringLiteral(literal));
+                        key
+                    },
+                    _ => return Err(String::from("Expected an object key")),
+                };
+                match tokens.next() {
+                    Some(colon @ Token::Colon(_)) => member.push(colon),
+                    _ => return Err(String::from("Expected ':' after object key")),
+                }
+                copy_sorted_value(tokens, &mut member, order, max_depth, depth + 1)?;
+                members.push((key, member));
+                match tokens.next() {
+                    Some(Token::Comma(_)) => continue,
+                    Some(close @ Token::CloseBrace(_)) => {
+                        if max_depth.is_none_or(|max| depth < max) {
+                            members.sort_by(|(a, _), (b, _)| compare_keys(a, b, order));
+                        }
+                        for (i, (_, member)) in members.into_iter().enumerate() {
+                            if i > 0 { out.push(Token::Comma(',')); }
+                            out.extend(member);
+                        }
+                        out.push(close);
+                        return Ok(());
+                    },
+                    _ => return Err(String::from("Expected ',' or '}' in object")),
+                }
+            }
+        },
+        Token::OpenBrack(_) => {
+            out.push(token);
+            if let Some(Token::CloseBrack(_)) = tokens.peek() {
+                out.extend(tokens.next());
+                return Ok(());
+            }
+            loop {
+                copy_sorted_value(tokens, out, order, max_depth, depth + 1)?;
+                match tokens.next() {
+                    Some(comma @ Token::Comma(_)) => out.push(comma),
+                    Some(close @ Token::CloseBrack(_)) => {
+                        out.push(close);
+                        return Ok(());
+                    },
+                    _ => return Err(String::from("Expected ',' or ']' in array")),
+                }
+            }
+        },
+        Token::StringLiteral(_) | Token::NumericLiteral(_) | Token::BoolLiteral(_) | Token::NullLiteral(_) => {
+            out.push(token);
+            Ok(())
+        },
+        token => Err(format!("Unexpected token '{}', expected a value", token.extract_value())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use super::{compare_keys, sort_value_keys, KeyOrder};
+    use crate::parse_json;
+
+    #[test]
+    fn test_compare_keys_natural() {
+        assert_eq!(compare_keys("9", "10", KeyOrder::Natural), Ordering::Less);
+        assert_eq!(compare_keys("9", "10", KeyOrder::Lexicographic), Ordering::Greater);
+        assert_eq!(compare_keys("item2b", "item10a", KeyOrder::Natural), Ordering::Less);
+        assert_eq!(compare_keys("a01", "a1", KeyOrder::Natural), Ordering::Greater);
+        assert_eq!(compare_keys("abc", "abc1", KeyOrder::Natural), Ordering::Less);
+    }
+
+    #[test]
+    fn test_compare_keys_case_insensitive() {
+        assert_eq!(compare_keys("apple", "Banana", KeyOrder::CaseInsensitive), Ordering::Less);
+        assert_eq!(compare_keys("apple", "Banana", KeyOrder::Lexicographic), Ordering::Greater);
+        assert_eq!(compare_keys("B", "b", KeyOrder::CaseInsensitive), Ordering::Less);
+    }
+
+    #[test]
+    fn test_sort_value_keys_depth() {
+        let mut value = parse_json(r#"{"b":{"y":1,"x":2},"a":[{"d":1,"c":2}]}"#).unwrap();
+        sort_value_keys(&mut value, KeyOrder::Lexicographic, Some(1));
+        assert_eq!(value.to_json_string(), r#"{"a":[{"d":1,"c":2}],"b":{"y":1,"x":2}}"#);
+        sort_value_keys(&mut value, KeyOrder::Lexicographic, None);
+        assert_eq!(value.to_json_string(), r#"{"a":[{"c":2,"d":1}],"b":{"x":2,"y":1}}"#);
+    }
+}
@@ -0,0 +1,505 @@
+use std::io::Read;
+
+use crate::number::scan_number;
+use crate::parser::{JSONParser, ParseOptions};
+use crate::pointer::{parse_array_index, parse_pointer};
+use crate::position::Span;
+use crate::value::{escape_json_string, unescape_json_string, JsonValue};
+use crate::{literal_name, StringScanner, Token};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Tokenizes JSON from a reader a buffer at a time, so that parts of a document
+/// can be picked out without holding all of it in memory. Whitespace is always
+/// skipped.
+pub struct JsonStream<R: Read> {
+    reader: R,
+    buffer: Vec<u8>,
+    buffer_pos: usize,
+    buffer_len: usize,
+    /// Byte offset of the next unread byte in the input.
+    offset: usize,
+    peeked: Option<Token>,
+    /// Where the token last read or peeked at is.
+    token_span: Span,
+    /// The containers `seek_pointer` is inside, outermost first: the key it
+    /// went through for objects, `None` for arrays.
+    path: Vec<Option<String>>,
+}
+
+impl<R: Read> JsonStream<R> {
+    pub fn new(reader: R) -> Self {
+        JsonStream { reader, buffer: vec![0; BUFFER_SIZE], buffer_pos: 0, buffer_len: 0, offset: 0, peeked: None, token_span: Span::default(), path: vec![] }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>, String> {
+        if self.buffer_pos == self.buffer_len {
+            self.buffer_len = loop {
+                match self.reader.read(&mut self.buffer) {
+                    Ok(n) => break n,
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(format!("Failed to read input: {}", err)),
+                }
+            };
+            self.buffer_pos = 0;
+            if self.buffer_len == 0 {
+                return Ok(None);
+            }
+        }
+        Ok(Some(self.buffer[self.buffer_pos]))
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, String> {
+        let byte = self.peek_byte()?;
+        if byte.is_some() {
+            self.buffer_pos += 1;
+            self.offset += 1;
+        }
+        Ok(byte)
+    }
+
+    pub fn peek_token(&mut self) -> Result<&Token, String> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.read_token(true)?);
+        }
+        Ok(self.peeked.as_ref().unwrap())
+    }
+
+    pub fn next_token(&mut self) -> Result<Token, String> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.read_token(true),
+        }
+    }
+
+    // With `keep_text` unset, string and number tokens come back empty, which
+    // saves building text that is only going to be skipped.
+    fn read_token(&mut self, keep_text: bool) -> Result<Token, String> {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek_byte()? {
+            self.next_byte()?;
+        }
+        let start = self.offset;
+        self.token_span = Span::new(start, start);
+        let byte = match self.next_byte()? {
+            Some(byte) => byte,
+            None => return Ok(Token::Eof),
+        };
+        let token = match byte {
+            b'{' => Token::OpenBrace('{'),
+            b'}' => Token::CloseBrace('}'),
+            b'[' => Token::OpenBrack('['),
+            b']' => Token::CloseBrack(']'),
+            b':' => Token::Colon(':'),
+            b',' => Token::Comma(','),
+            b'"' => Token::StringLiteral(self.read_string(start, keep_text)?),
+            b'-' | b'0'..=b'9' => {
+                // gather everything that can be part of a number, then hold it to the lexer's grammar
+                let mut text = vec![byte];
+                while let Some(b) = self.peek_byte()?.filter(|b| matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')) {
+                    text.push(b);
+                    self.next_byte()?;
+                }
+                match scan_number(&text) {
+                    Ok(len) if len == text.len() => (),
+                    Ok(_) => return Err(format!("Invalid number '{}' at position {}", String::from_utf8_lossy(&text), start)),
+                    Err(reason) => return Err(format!("Invalid number found at position {}; {}", start, reason)),
+                }
+                Token::NumericLiteral(if keep_text { String::from_utf8(text).unwrap() } else { String::new() })
+            },
+            b't' | b'f' | b'n' => {
+                let mut word = vec![byte];
+                while let Some(b) = self.peek_byte()?.filter(u8::is_ascii_alphabetic) {
+                    word.push(b);
+                    self.next_byte()?;
+                }
+                let word = String::from_utf8(word).unwrap();
+                literal_name(&word).ok_or_else(|| format!("Unexpected '{}' at position {}", word, start))?
+            },
+            _ => return Err(format!("Unexpected character '{}' at position {}", byte as char, start)),
+        };
+        self.token_span = Span::new(start, self.offset);
+        Ok(token)
+    }
+
+    // the raw literal, quotes included, as the main lexer gives it
+    fn read_string(&mut self, start: usize, keep_text: bool) -> Result<String, String> {
+        let mut bytes = vec![b'"'];
+        let mut scanner = StringScanner::default();
+        loop {
+            let byte = match self.next_byte()? {
+                Some(byte) => byte,
+                None => return Err(format!("Unterminated string literal at position {}", start)),
+            };
+            if keep_text {
+                bytes.push(byte);
+            }
+            if scanner.is_closing(byte) {
+                break;
+            }
+        }
+        if !keep_text {
+            return Ok(String::new());
+        }
+        String::from_utf8(bytes).map_err(|_| format!("Invalid UTF-8 in string starting at position {}", start))
+    }
+
+    /// Reads past one complete value without keeping any of it. Brackets have
+    /// to match, but nothing else about the structure inside is checked.
+    pub fn skip_value(&mut self) -> Result<(), String> {
+        // the closing brackets of the containers we are inside, innermost last
+        let mut closers = vec![];
+        loop {
+            let token = match self.peeked.take() {
+                Some(token) => token,
+                None => self.read_token(false)?,
+            };
+            match token {
+                Token::OpenBrace(_) => closers.push(Token::CloseBrace('}')),
+                Token::OpenBrack(_) => closers.push(Token::CloseBrack(']')),
+                Token::CloseBrace(_) | Token::CloseBrack(_) if closers.last() == Some(&token) => {
+                    closers.pop();
+                },
+                Token::StringLiteral(_) | Token::NumericLiteral(_) | Token::BoolLiteral(_) | Token::NullLiteral(_) => (),
+                Token::Colon(_) | Token::Comma(_) if !closers.is_empty() => (),
+                Token::Eof => return Err(String::from("Unexpected end of input, expected a value")),
+                token => return Err(format!("Unexpected token '{}' at position {}", token.extract_value(), self.offset - 1)),
+            }
+            if closers.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads one complete value. Nothing after it is looked at.
+    pub fn read_value(&mut self) -> Result<JsonValue, String> {
+        Ok(self.value_parser()?.parse()?)
+    }
+
+    /// Reads one complete value as its minified source: the tokens as they were
+    /// written, without the whitespace between them.
+    pub fn read_value_text(&mut self) -> Result<String, String> {
+        let mut parser = self.value_parser()?;
+        let text = parser.tokens.iter().map(|token| token.clone().extract_value()).collect();
+        parser.parse()?;
+        Ok(text)
+    }
+
+    // a parser over the tokens of the next value, with their spans for its errors
+    fn value_parser(&mut self) -> Result<JSONParser, String> {
+        let mut tokens = vec![];
+        let mut spans = vec![];
+        let mut depth = 0usize;
+        loop {
+            let token = self.next_token()?;
+            spans.push(self.token_span);
+            match token {
+                Token::OpenBrace(_) | Token::OpenBrack(_) => depth += 1,
+                Token::CloseBrace(_) | Token::CloseBrack(_) => depth = depth.saturating_sub(1),
+                Token::Eof => return Err(String::from("Unexpected end of input, expected a value")),
+                _ => (),
+            }
+            tokens.push(token);
+            if depth == 0 {
+                let mut parser = JSONParser::from(tokens, ParseOptions::default());
+                parser.spans = spans;
+                return Ok(parser);
+            }
+        }
+    }
+
+    /// Moves to the value at `pointer`, skipping everything before it, so that
+    /// the next token read is its first. Gives `false` if there is no such value.
+    /// Call `finish_pointer` once done with the value.
+    pub fn seek_pointer(&mut self, pointer: &str) -> Result<bool, String> {
+        self.path.clear();
+        for reference in parse_pointer(pointer)? {
+            let found = match (self.peek_token()?, parse_array_index(&reference)) {
+                (Token::OpenBrace(_), _) => {
+                    self.next_token()?;
+                    let found = self.seek_member(&reference)?;
+                    self.path.push(Some(reference));
+                    found
+                },
+                (Token::OpenBrack(_), Some(index)) => {
+                    self.next_token()?;
+                    let found = self.seek_element(index)?;
+                    self.path.push(None);
+                    found
+                },
+                (Token::Eof, _) => return Err(String::from("Unexpected end of input, expected a value")),
+                _ => {
+                    self.skip_value()?;
+                    return Ok(false);
+                },
+            };
+            if !found {
+                // the container has been read to its end
+                self.path.pop();
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reads the rest of the containers `seek_pointer` went into, after the
+    /// value it found, and checks that nothing follows the document. Parsing
+    /// takes the last of repeated keys, which a stream can't go back for, so a
+    /// key the pointer went through that comes again is an error.
+    pub fn finish_pointer(&mut self) -> Result<(), String> {
+        while let Some(wanted) = self.path.pop() {
+            loop {
+                match (self.next_token()?, &wanted) {
+                    (Token::Comma(_), _) => (),
+                    (Token::CloseBrace(_), Some(_)) | (Token::CloseBrack(_), None) => break,
+                    (Token::Eof, _) => return Err(String::from("Unexpected end of input")),
+                    (token, _) => return Err(format!("Unexpected token '{}' at position {}", token.extract_value(), self.offset - 1)),
+                }
+                if let Some(wanted) = &wanted {
+                    let (key, start) = self.read_key()?;
+                    if key == *wanted {
+                        return Err(format!("Duplicate key {} at position {}; a streamed pointer can't tell which one to take",
+                            escape_json_string(&key), start));
+                    }
+                }
+                self.skip_value()?;
+            }
+        }
+        match self.next_token()? {
+            Token::Eof => Ok(()),
+            token => Err(format!("Unexpected token '{}' after the end of the document", token.extract_value())),
+        }
+    }
+
+    // an object key and the colon after it, with where the key starts
+    fn read_key(&mut self) -> Result<(String, usize), String> {
+        let key = match self.next_token()? {
+            Token::StringLiteral(literal) => (unescape_json_string(&literal)?, self.offset - literal.len()),
+            token => return Err(format!("Expected an object key, found '{}' at position {}", token.extract_value(), self.offset)),
+        };
+        match self.next_token()? {
+            Token::Colon(_) => Ok(key),
+            _ => Err(format!("Expected ':' after object key at position {}", self.offset)),
+        }
+    }
+
+    fn seek_member(&mut self, wanted: &str) -> Result<bool, String> {
+        if let Token::CloseBrace(_) = self.peek_token()? {
+            self.next_token()?;
+            return Ok(false);
+        }
+        loop {
+            if self.read_key()?.0 == wanted {
+                return Ok(true);
+            }
+            self.skip_value()?;
+            match self.next_token()? {
+                Token::Comma(_) => (),
+                Token::CloseBrace(_) => return Ok(false),
+                _ => return Err(format!("Expected ',' or '}}' in object at position {}", self.offset)),
+            }
+        }
+    }
+
+    fn seek_element(&mut self, index: usize) -> Result<bool, String> {
+        if let Token::CloseBrack(_) = self.peek_token()? {
+            self.next_token()?;
+            return Ok(false);
+        }
+        for _ in 0..index {
+            self.skip_value()?;
+            match self.next_token()? {
+                Token::Comma(_) => (),
+                Token::CloseBrack(_) => return Ok(false),
+                _ => return Err(format!("Expected ',' or ']' in array at position {}", self.offset)),
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Reads the value at `pointer` from `reader`, keeping nothing else. The rest
+/// of the input is still read through, to find repeats of the keys on the way
+/// to the value; see `JsonStream::finish_pointer`.
+pub fn extract_pointer<R: Read>(reader: R, pointer: &str) -> Result<Option<JsonValue>, String> {
+    let mut stream = JsonStream::new(reader);
+    let value = if stream.seek_pointer(pointer)? { Some(stream.read_value()?) } else { None };
+    stream.finish_pointer()?;
+    Ok(value)
+}
+
+/// Iterates over the elements of one array in a stream, parsing one element at
+/// a time, so memory use is bounded by the largest element rather than the array.
+pub struct ArrayElements<R: Read> {
+    stream: JsonStream<R>,
+    first: bool,
+    done: bool,
+}
+
+impl<R: Read> Iterator for ArrayElements<R> {
+    type Item = Result<JsonValue, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with(JsonStream::read_value)
+    }
+}
+
+impl<R: Read> ArrayElements<R> {
+    /// The next element as its minified source rather than parsed, so strings
+    /// and numbers come out exactly as they were written.
+    pub fn next_text(&mut self) -> Option<Result<String, String>> {
+        self.next_with(JsonStream::read_value_text)
+    }
+
+    fn next_with<T>(&mut self, read: fn(&mut JsonStream<R>) -> Result<T, String>) -> Option<Result<T, String>> {
+        if self.done {
+            return None;
+        }
+        let result = match self.has_next() {
+            Ok(true) => read(&mut self.stream).map(Some),
+            Ok(false) => Ok(None),
+            Err(err) => Err(err),
+        };
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result.transpose()
+    }
+
+    // moves past the separator before the next element, if there is one
+    fn has_next(&mut self) -> Result<bool, String> {
+        if self.first {
+            self.first = false;
+            if let Token::CloseBrack(_) = self.stream.peek_token()? {
+                self.stream.next_token()?;
+                return self.end();
+            }
+            return Ok(true);
+        }
+        match self.stream.next_token()? {
+            Token::Comma(_) => Ok(true),
+            Token::CloseBrack(_) => self.end(),
+            _ => Err(format!("Expected ',' or ']' in array at position {}", self.stream.offset())),
+        }
+    }
+
+    fn end(&mut self) -> Result<bool, String> {
+        self.stream.finish_pointer()?;
+        Ok(false)
+    }
+}
+
+/// Streams the elements of the array at `pointer`, e.g. `iter_array_at(file, "/txIndexes")`.
+/// After the last element the rest of the input is read as by `extract_pointer`.
+pub fn iter_array_at<R: Read>(reader: R, pointer: &str) -> Result<ArrayElements<R>, String> {
+    let mut stream = JsonStream::new(reader);
+    if !stream.seek_pointer(pointer)? {
+        return Err(format!("Nothing at '{}'", pointer));
+    }
+    match stream.next_token()? {
+        Token::OpenBrack(_) => Ok(ArrayElements { stream, first: true, done: false }),
+        token => Err(format!("Expected an array at '{}', found '{}'", pointer, token.extract_value())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Read;
+    use super::{extract_pointer, iter_array_at};
+    use crate::parse_json;
+
+    // hands out a few bytes per read, so tokens straddle buffer refills
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_extract_pointer() {
+        let input = r#"{"a":{"b\"/":[10,{"c":"x y"},true]},"b":null}"#;
+        let at = |pointer: &str| extract_pointer(Trickle(input.as_bytes()), pointer).unwrap().map(|v| v.to_json_string());
+        assert_eq!(at("/a/b\"~1/1"), Some(String::from(r#"{"c":"x y"}"#)));
+        assert_eq!(at("/a/b\"~1/2"), Some(String::from("true")));
+        assert_eq!(at("/b"), Some(String::from("null")));
+        assert_eq!(at(""), Some(parse_json(input).unwrap().to_json_string()));
+        assert_eq!(at("/a/b\"~1/3"), None);
+        assert_eq!(at("/a/x"), None);
+        assert_eq!(at("/b/0"), None);
+    }
+
+    #[test]
+    fn test_extract_reads_the_rest() {
+        let input = r#"{"first":[1,2],"rest": this is not json"#;
+        assert!(extract_pointer(input.as_bytes(), "/first").is_err());
+        assert!(extract_pointer(r#"{"x":[1,2},"y":3}"#.as_bytes(), "/y").is_err());
+        assert!(extract_pointer(r#"[[{]}], 1]"#.as_bytes(), "/1").is_err());
+        assert_eq!(extract_pointer(r#"[[{}, []], 1]"#.as_bytes(), "/1").unwrap(), Some(parse_json("1").unwrap()));
+        assert!(extract_pointer("[1] 2".as_bytes(), "/0").is_err());
+        assert_eq!(extract_pointer(r#"[{"a":1}, 2]"#.as_bytes(), "/0/b").unwrap(), None);
+    }
+
+    #[test]
+    fn test_extract_repeated_keys() {
+        let err = extract_pointer(Trickle(r#"{"x":1, "x":2}"#.as_bytes()), "/x").unwrap_err();
+        assert_eq!(err, "Duplicate key \"x\" at position 8; a streamed pointer can't tell which one to take");
+        assert!(extract_pointer(r#"{"a":{"b":1},"c":0,"a":{}}"#.as_bytes(), "/a/b").is_err());
+        assert!(extract_pointer(r#"{"a":{"b":1,"b":2}}"#.as_bytes(), "/a/c").is_ok());
+        assert!(iter_array_at(r#"{"a":[1],"a":[2]}"#.as_bytes(), "/a").unwrap().any(|item| item.is_err()));
+        // repeats off the path don't matter
+        assert_eq!(extract_pointer(r#"{"y":1,"x":2,"y":3}"#.as_bytes(), "/x").unwrap(), Some(parse_json("2").unwrap()));
+    }
+
+    #[test]
+    fn test_errors_match_lexer() {
+        for input in ["[01]", "[1.]", "[2e+]", "[\"a\\\"]"] {
+            let streamed = extract_pointer(Trickle(input.as_bytes()), "/0").unwrap_err();
+            assert_eq!(streamed, parse_json(input).unwrap_err().to_string(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_extract_from_fixtures() {
+        let file = File::open("testfiles/noaa_climate_test.json").unwrap();
+        let value = extract_pointer(file, "/data/189512").unwrap().unwrap();
+        assert_eq!(value.to_json_string(), r#"{"value":"27.55","anomaly":"-2.39"}"#);
+        let text = fs::read_to_string("testfiles/massive_arr_test.json").unwrap();
+        let whole = parse_json(&text).unwrap();
+        let streamed = extract_pointer(File::open("testfiles/massive_arr_test.json").unwrap(), "/txIndexes").unwrap();
+        assert_eq!(streamed.as_ref(), whole.pointer("/txIndexes"));
+    }
+
+    #[test]
+    fn test_iter_array_at() {
+        let input = r#"{"skip":[[1]],"items":[1, {"a":[2]} ,"three"],"after":0}"#;
+        let items: Vec<String> = iter_array_at(Trickle(input.as_bytes()), "/items").unwrap()
+            .map(|item| item.unwrap().to_json_string()).collect();
+        assert_eq!(items, ["1", r#"{"a":[2]}"#, r#""three""#]);
+        assert_eq!(iter_array_at("[]".as_bytes(), "").unwrap().count(), 0);
+        assert!(iter_array_at(input.as_bytes(), "/after").is_err());
+        assert!(iter_array_at(input.as_bytes(), "/missing").is_err());
+
+        let mut broken = iter_array_at("[1,2 3]".as_bytes(), "").unwrap();
+        assert!(broken.next().unwrap().is_ok());
+        assert!(broken.next().unwrap().is_ok());
+        assert!(broken.next().unwrap().is_err());
+        assert!(broken.next().is_none());
+    }
+
+    #[test]
+    fn test_iter_array_at_fixture() {
+        let whole = parse_json(&fs::read_to_string("testfiles/massive_arr_test.json").unwrap()).unwrap();
+        let file = File::open("testfiles/massive_arr_test.json").unwrap();
+        let streamed: Vec<_> = iter_array_at(file, "/txIndexes").unwrap().map(Result::unwrap).collect();
+        assert_eq!(Some(&streamed), whole.pointer("/txIndexes").and_then(|v| v.as_array()));
+    }
+}
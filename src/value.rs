@@ -0,0 +1,308 @@
+use std::fmt;
+
+use crate::number::JsonNumber;
+use crate::object::JsonObject;
+use crate::pointer::{parse_pointer, resolve_pointer, resolve_pointer_mut};
+use crate::prettify_json;
+
+/// A parsed JSON document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    /// The number exactly as it was written in the source.
+    Number(JsonNumber),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(JsonObject),
+}
+
+impl JsonValue {
+    /// Name of the JSON type, for error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            JsonValue::Null => "null",
+            JsonValue::Bool(_) => "boolean",
+            JsonValue::Number(_) => "number",
+            JsonValue::String(_) => "string",
+            JsonValue::Array(_) => "array",
+            JsonValue::Object(_) => "object",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&JsonNumber> {
+        match self {
+            JsonValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&JsonObject> {
+        match self {
+            JsonValue::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut JsonObject> {
+        match self {
+            JsonValue::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` if this is an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object().and_then(|obj| obj.get(key))
+    }
+
+    /// Looks up a JSON Pointer (RFC 6901) such as `/web-app/servlet/0`. Gives
+    /// `None` if the pointer is malformed or leads nowhere.
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        resolve_pointer(self, &parse_pointer(pointer).ok()?)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonValue> {
+        resolve_pointer_mut(self, &parse_pointer(pointer).ok()?)
+    }
+
+    /// Serializes without any whitespace, members in the object's own order.
+    pub fn to_json_string(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+
+    /// Serializes through `prettify_json`, so the layout matches the CLI output.
+    pub fn to_pretty_string(&self) -> Result<String, String> {
+        prettify_json(self.to_json_string())
+    }
+
+    fn write_json(&self, out: &mut String) {
+        match self {
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            JsonValue::Number(n) => out.push_str(n.as_str()),
+            JsonValue::String(s) => out.push_str(&escape_json_string(s)),
+            JsonValue::Array(arr) => {
+                out.push('[');
+                for (i, item) in arr.iter().enumerate() {
+                    if i > 0 { out.push(','); }
+                    item.write_json(out);
+                }
+                out.push(']');
+            },
+            JsonValue::Object(obj) => {
+                out.push('{');
+                for (i, (key, value)) in obj.iter().enumerate() {
+                    if i > 0 { out.push(','); }
+                    out.push_str(&escape_json_string(key));
+                    out.push(':');
+                    value.write_json(out);
+                }
+                out.push('}');
+            },
+        }
+    }
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_json_string())
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> Self {
+        JsonValue::Bool(b)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(s: &str) -> Self {
+        JsonValue::String(String::from(s))
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(s: String) -> Self {
+        JsonValue::String(s)
+    }
+}
+
+impl From<JsonNumber> for JsonValue {
+    fn from(n: JsonNumber) -> Self {
+        JsonValue::Number(n)
+    }
+}
+
+impl From<i64> for JsonValue {
+    fn from(n: i64) -> Self {
+        JsonValue::Number(JsonNumber::from(n))
+    }
+}
+
+impl From<u64> for JsonValue {
+    fn from(n: u64) -> Self {
+        JsonValue::Number(JsonNumber::from(n))
+    }
+}
+
+impl From<Vec<JsonValue>> for JsonValue {
+    fn from(arr: Vec<JsonValue>) -> Self {
+        JsonValue::Array(arr)
+    }
+}
+
+impl From<JsonObject> for JsonValue {
+    fn from(obj: JsonObject) -> Self {
+        JsonValue::Object(obj)
+    }
+}
+
+/// Quotes `s` as a JSON string literal, escaping only what JSON requires.
+pub fn escape_json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Decodes a string literal as produced by the lexer (quotes included).
+pub fn unescape_json_string(literal: &str) -> Result<String, String> {
+    let inner = literal.strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(|| format!("Not a string literal: {}", literal))?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('"') => out.push('"'),
+                Some('\\') => out.push('\\'),
+                Some('/') => out.push('/'),
+                Some('b') => out.push('\u{08}'),
+                Some('f') => out.push('\u{0c}'),
+                Some('n') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some('t') => out.push('\t'),
+                Some('u') => {
+                    let high = read_hex4(&mut chars)?;
+                    let code = if (0xD800..0xDC00).contains(&high) {
+                        // a high surrogate has to be followed by an escaped low surrogate
+                        if chars.next() != Some('\\') || chars.next() != Some('u') {
+                            return Err(format!("Unpaired surrogate \\u{:04x} in string literal", high));
+                        }
+                        let low = read_hex4(&mut chars)?;
+                        if !(0xDC00..0xE000).contains(&low) {
+                            return Err(format!("Unpaired surrogate \\u{:04x} in string literal", high));
+                        }
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    } else {
+                        high
+                    };
+                    match char::from_u32(code) {
+                        Some(ch) => out.push(ch),
+                        None => return Err(format!("Unpaired surrogate \\u{:04x} in string literal", code)),
+                    }
+                },
+                Some(other) => return Err(format!("Invalid escape sequence '\\{}' in string literal", other)),
+                None => return Err(String::from("Unterminated escape sequence in string literal")),
+            },
+            c if (c as u32) < 0x20 => {
+                return Err(format!("Unescaped control character U+{:04X} in string literal", c as u32));
+            },
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+fn read_hex4(chars: &mut std::str::Chars) -> Result<u32, String> {
+    let digits: String = chars.by_ref().take(4).collect();
+    if digits.chars().count() != 4 {
+        return Err(String::from("Truncated \\u escape in string literal"));
+    }
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid \\u escape '\\u{}' in string literal", digits));
+    }
+    Ok(u32::from_str_radix(&digits, 16).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::{escape_json_string, unescape_json_string, JsonValue};
+    use crate::parse_json;
+
+    #[test]
+    fn test_unescape_json_string() {
+        assert_eq!(unescape_json_string(r#""plain""#).unwrap(), "plain");
+        assert_eq!(unescape_json_string(r#""a\"b\\c\/d\n""#).unwrap(), "a\"b\\c/d\n");
+        assert_eq!(unescape_json_string(r#""\u00e9\ud83d\ude00""#).unwrap(), "\u{e9}\u{1F600}");
+        assert!(unescape_json_string(r#""\ud83d""#).is_err());
+        assert!(unescape_json_string(r#""\x""#).is_err());
+    }
+
+    #[test]
+    fn test_escape_json_string() {
+        assert_eq!(escape_json_string("a\"b\\c\u{1}\u{e9}"), r#""a\"b\\c\u0001é""#);
+        assert_eq!(unescape_json_string(&escape_json_string("tab\there")).unwrap(), "tab\there");
+    }
+
+    #[test]
+    fn test_pointer() {
+        let mut value = parse_json(&fs::read_to_string("testfiles/long_test.json").unwrap()).unwrap();
+        let param = value.pointer("/web-app/servlet/0/init-param").unwrap();
+        assert_eq!(param.pointer("/templatePath").and_then(JsonValue::as_str), Some("templates"));
+        assert!(value.pointer("/web-app/servlet/99").is_none());
+        assert!(value.pointer("web-app").is_none());
+        *value.pointer_mut("/web-app/servlet/0/servlet-name").unwrap() = JsonValue::from("renamed");
+        assert_eq!(value.pointer("/web-app/servlet/0/servlet-name").and_then(JsonValue::as_str), Some("renamed"));
+    }
+}